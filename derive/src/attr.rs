@@ -0,0 +1,97 @@
+use syn::{meta::ParseNestedMeta, Attribute, Expr, LitStr, Result};
+
+/// The `#[msgpack(...)]` attributes of a container or field.
+#[derive(Default)]
+pub(crate) struct MsgpackAttrs {
+    /// `max_len = <expr>`: bound used by `MaxEncodedLen` instead of the one of the field type
+    pub max_len: Option<Expr>,
+    /// `rename = "name"`: key written by `SerializeIntoSlice` and accepted by `DeserializeFromSlice`
+    pub rename: Option<String>,
+    /// `skip`: the field is neither written nor read
+    pub skip: bool,
+    /// `array` or `map`: layout of a struct with named fields, defaults to `map`
+    pub array: bool,
+}
+
+impl MsgpackAttrs {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut result = MsgpackAttrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("msgpack")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("max_len") {
+                    result.max_len = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("rename") {
+                    result.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("skip") {
+                    result.skip = true;
+                } else if meta.path.is_ident("array") {
+                    result.array = true;
+                } else if meta.path.is_ident("map") {
+                    result.array = false;
+                } else {
+                    return Err(meta.error("unsupported msgpack attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(result)
+    }
+}
+
+/// The serde attributes that change the encoded names.
+///
+/// Attributes that add entries or make serde write maps of unknown length are rejected, because the bound would not
+/// hold for them.
+#[derive(Default)]
+pub(crate) struct SerdeAttrs {
+    pub rename: Option<String>,
+    pub rename_all: bool,
+}
+
+impl SerdeAttrs {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut result = SerdeAttrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    if meta.input.peek(syn::Token![=]) {
+                        result.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                        Ok(())
+                    } else {
+                        meta.parse_nested_meta(|meta| {
+                            if meta.path.is_ident("serialize") {
+                                result.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                                Ok(())
+                            } else {
+                                skip(meta)
+                            }
+                        })
+                    }
+                } else if meta.path.is_ident("rename_all") || meta.path.is_ident("rename_all_fields") {
+                    result.rename_all = true;
+                    skip(meta)
+                } else if ["tag", "content", "untagged", "flatten"]
+                    .iter()
+                    .any(|name| meta.path.is_ident(name))
+                {
+                    Err(meta.error("this serde attribute is not supported by MaxEncodedLen"))
+                } else {
+                    skip(meta)
+                }
+            })?;
+        }
+        Ok(result)
+    }
+}
+
+/// Consumes an attribute argument that is not of interest.
+fn skip(meta: ParseNestedMeta) -> Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+        Ok(())
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(skip)
+    } else {
+        Ok(())
+    }
+}
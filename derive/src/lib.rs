@@ -0,0 +1,55 @@
+//! Derive macros for [`embedded-msgpack`](https://docs.rs/embedded-msgpack).
+//!
+//! Use them through the `derive` feature of `embedded-msgpack` instead of depending on this crate directly.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod attr;
+mod max_len;
+mod slice;
+
+/// Derives `MaxEncodedLen` for structs and enums whose fields implement it.
+///
+/// The result covers the encoding with field and variant names as well as with
+/// `Config::with_struct_as_array` and `Config::with_variant_index`, and takes `#[serde(rename)]` into account.
+/// `#[serde(tag)]`, `content`, `untagged` and `flatten` change the layout and are rejected.
+/// Fields of types without a static bound, like `&str` or `Ext`, need an explicit one:
+///
+/// ```ignore
+/// #[derive(Serialize, MaxEncodedLen)]
+/// struct Reading<'a> {
+///     #[msgpack(max_len = max_len::str_len(16))]
+///     sensor: &'a str,
+///     value: f32,
+/// }
+/// ```
+#[proc_macro_derive(MaxEncodedLen, attributes(msgpack))]
+pub fn derive_max_encoded_len(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    max_len::derive(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Derives `SerializeIntoSlice` for structs whose fields implement it.
+///
+/// Structs with named fields are written as maps from field names to values, or as arrays of the values with
+/// `#[msgpack(array)]`. Tuple structs are written as arrays and newtypes as their content. Fields can be renamed
+/// with `#[msgpack(rename = "name")]` and left out with `#[msgpack(skip)]`.
+#[proc_macro_derive(SerializeIntoSlice, attributes(msgpack))]
+pub fn derive_serialize_into_slice(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    slice::derive_serialize(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Derives `DeserializeFromSlice` for structs whose fields implement it, reading the layout written by the
+/// `SerializeIntoSlice` derive with the same attributes.
+///
+/// Fields are updated in place: fields missing from the input and skipped fields keep their value, unknown map
+/// entries and surplus array elements are skipped.
+#[proc_macro_derive(DeserializeFromSlice, attributes(msgpack))]
+pub fn derive_deserialize_from_slice(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    slice::derive_deserialize(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,92 @@
+use crate::attr::{MsgpackAttrs, SerdeAttrs};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Fields, Result};
+
+pub(crate) fn derive(mut input: DeriveInput) -> Result<TokenStream2> {
+    let m = max_len_module();
+    let container = SerdeAttrs::parse(&input.attrs)?;
+    let len = match &input.data {
+        Data::Struct(data) => fields_len(&data.fields, container.rename_all)?,
+        Data::Enum(data) => {
+            let mut len = quote!(0);
+            for (index, variant) in data.variants.iter().enumerate() {
+                let attrs = SerdeAttrs::parse(&variant.attrs)?;
+                let tag_len = name_len(&variant.ident.to_string(), &attrs, None, container.rename_all);
+                let index = index as u64;
+                let tag = quote!(#m::max(#tag_len, #m::uint_len(#index)));
+                let rename_all = container.rename_all || attrs.rename_all;
+                let variant_len = match &variant.fields {
+                    Fields::Unit => tag,
+                    fields => {
+                        let payload = fields_len(fields, rename_all)?;
+                        quote!(#m::map_header_len(1) + #tag + #payload)
+                    }
+                };
+                len = quote!(#m::max(#len, #variant_len));
+            }
+            len
+        }
+        Data::Union(data) => return Err(syn::Error::new(data.union_token.span, "unions are not supported")),
+    };
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::embedded_msgpack::encode::MaxEncodedLen));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::embedded_msgpack::encode::MaxEncodedLen for #name #ty_generics #where_clause {
+            const MAX_ENCODED_LEN: usize = #len;
+        }
+    })
+}
+
+/// Path of the module with the `const fn` helpers the generated code uses.
+fn max_len_module() -> TokenStream2 { quote!(::embedded_msgpack::encode::max_len) }
+
+/// Maximum length of a struct or the payload of an enum variant with `fields`.
+fn fields_len(fields: &Fields, rename_all: bool) -> Result<TokenStream2> {
+    let m = max_len_module();
+    let mut len = quote!(0);
+    for field in fields.iter() {
+        let msgpack = MsgpackAttrs::parse(&field.attrs)?;
+        let field_len = match &msgpack.max_len {
+            Some(expr) => quote!((#expr)),
+            None => {
+                let ty = &field.ty;
+                quote!(<#ty as ::embedded_msgpack::encode::MaxEncodedLen>::MAX_ENCODED_LEN)
+            }
+        };
+        len = match &field.ident {
+            Some(ident) => {
+                let serde = SerdeAttrs::parse(&field.attrs)?;
+                let key_len = name_len(&ident.to_string(), &serde, msgpack.rename.as_deref(), rename_all);
+                quote!(#len + #key_len + #field_len)
+            }
+            None => quote!(#len + #field_len),
+        };
+    }
+    let n = fields.len();
+    Ok(match fields {
+        Fields::Named(_) => quote!(#m::map_header_len(#n) + #len),
+        // newtypes are encoded as their content
+        Fields::Unnamed(_) if n == 1 => len,
+        Fields::Unnamed(_) => quote!(#m::array_header_len(#n) + #len),
+        Fields::Unit => quote!(1),
+    })
+}
+
+/// Maximum encoded length of a field or variant name, as written by serde or the `SerializeIntoSlice` derive.
+fn name_len(ident: &str, attrs: &SerdeAttrs, msgpack_rename: Option<&str>, rename_all: bool) -> TokenStream2 {
+    let m = max_len_module();
+    let ident = ident.strip_prefix("r#").unwrap_or(ident);
+    let serde_len = match &attrs.rename {
+        Some(name) => name.len(),
+        // every rename rule at most inserts a separator before each upper case letter
+        None if rename_all => ident.len() + ident.chars().filter(char::is_ascii_uppercase).count(),
+        None => ident.len(),
+    };
+    let len = serde_len.max(msgpack_rename.map_or(ident.len(), str::len));
+    quote!(#m::str_len(#len))
+}
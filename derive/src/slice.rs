@@ -0,0 +1,157 @@
+use crate::attr::MsgpackAttrs;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Fields, Index, Member, Result};
+
+/// A field that is written and read, with its encoded name.
+struct Field {
+    member: Member,
+    name: String,
+}
+
+/// Collects the fields of a struct that are not skipped.
+fn fields(input: &DeriveInput) -> Result<(&Fields, Vec<Field>)> {
+    let data = match &input.data {
+        Data::Struct(data) => data,
+        Data::Enum(data) => return Err(syn::Error::new(data.enum_token.span, "enums are not supported")),
+        Data::Union(data) => return Err(syn::Error::new(data.union_token.span, "unions are not supported")),
+    };
+    let mut fields = Vec::new();
+    for (i, field) in data.fields.iter().enumerate() {
+        let attrs = MsgpackAttrs::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let (member, name) = match &field.ident {
+            Some(ident) => {
+                let name = ident.to_string();
+                let name = name.strip_prefix("r#").unwrap_or(&name).to_owned();
+                (Member::Named(ident.clone()), name)
+            }
+            None => (Member::Unnamed(Index::from(i)), i.to_string()),
+        };
+        fields.push(Field {
+            member,
+            name: attrs.rename.unwrap_or(name),
+        });
+    }
+    Ok((&data.fields, fields))
+}
+
+pub(crate) fn derive_serialize(mut input: DeriveInput) -> Result<TokenStream2> {
+    let array = MsgpackAttrs::parse(&input.attrs)?.array;
+    let (kind, fields) = fields(&input)?;
+    let n = fields.len();
+    let body = match kind {
+        Fields::Unit => quote! {
+            ::embedded_msgpack::encode::SerializeIntoSlice::write_into(&::core::option::Option::<()>::None, writer)
+        },
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 && n == 1 => {
+            let member = &fields[0].member;
+            quote!(::embedded_msgpack::encode::SerializeIntoSlice::write_into(&self.#member, writer))
+        }
+        Fields::Named(_) if !array => {
+            let entries = fields.iter().map(|Field { member, name }| {
+                quote! {
+                    len += ::embedded_msgpack::encode::serialize_map_kay_value(&#name, &self.#member, &mut writer)?;
+                }
+            });
+            quote! {
+                let mut len = ::embedded_msgpack::encode::serialize_map_start(#n, &mut writer)?;
+                #(#entries)*
+                ::core::result::Result::Ok(len)
+            }
+        }
+        _ => {
+            let members = fields.iter().map(|f| &f.member);
+            quote! {
+                let mut len = ::embedded_msgpack::encode::serialize_array_start(#n, &mut writer)?;
+                #(len += ::embedded_msgpack::encode::SerializeIntoSlice::write_into(&self.#members, &mut writer)?;)*
+                ::core::result::Result::Ok(len)
+            }
+        }
+    };
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::embedded_msgpack::encode::SerializeIntoSlice));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::embedded_msgpack::encode::SerializeIntoSlice for #name #ty_generics #where_clause {
+            #[allow(unused_mut)]
+            fn write_into<W: ::embedded_msgpack::encode::Write>(
+                &self,
+                mut writer: W,
+            ) -> ::core::result::Result<usize, ::embedded_msgpack::encode::Error> {
+                #body
+            }
+        }
+    })
+}
+
+pub(crate) fn derive_deserialize(mut input: DeriveInput) -> Result<TokenStream2> {
+    let array = MsgpackAttrs::parse(&input.attrs)?.array;
+    let (kind, fields) = fields(&input)?;
+    let body = match kind {
+        Fields::Unit => quote! {
+            match buf.first() {
+                // nil
+                ::core::option::Option::Some(0xc0) => ::core::result::Result::Ok(1),
+                ::core::option::Option::Some(_) => ::core::result::Result::Err(::embedded_msgpack::decode::Error::InvalidType),
+                ::core::option::Option::None => ::core::result::Result::Err(::embedded_msgpack::decode::Error::EndOfBuffer),
+            }
+        },
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 && fields.len() == 1 => {
+            let member = &fields[0].member;
+            quote!(::embedded_msgpack::decode::DeserializeFromSlice::from_slice(&mut self.#member, buf))
+        }
+        Fields::Named(_) if !array => {
+            let arms = fields.iter().map(|Field { member, name }| {
+                quote!(#name => ::embedded_msgpack::decode::DeserializeFromSlice::from_slice(&mut self.#member, &buf[pos..])?,)
+            });
+            quote! {
+                let (n, mut pos) = ::embedded_msgpack::decode::read_map_len(buf)?;
+                for _ in 0..n {
+                    let (key, key_len) = ::embedded_msgpack::decode::read_str(&buf[pos..])?;
+                    pos += key_len;
+                    pos += match key {
+                        #(#arms)*
+                        _ => ::embedded_msgpack::decode::skip_any(&buf[pos..])?.1,
+                    };
+                }
+                ::core::result::Result::Ok(pos)
+            }
+        }
+        _ => {
+            let arms = fields.iter().enumerate().map(|(i, Field { member, .. })| {
+                quote!(#i => ::embedded_msgpack::decode::DeserializeFromSlice::from_slice(&mut self.#member, &buf[pos..])?,)
+            });
+            quote! {
+                let (n, mut pos) = ::embedded_msgpack::decode::read_array_len(buf)?;
+                for i in 0..n {
+                    pos += match i {
+                        #(#arms)*
+                        _ => ::embedded_msgpack::decode::skip_any(&buf[pos..])?.1,
+                    };
+                }
+                ::core::result::Result::Ok(pos)
+            }
+        }
+    };
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::embedded_msgpack::decode::DeserializeFromSlice));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::embedded_msgpack::decode::DeserializeFromSlice for #name #ty_generics #where_clause {
+            fn from_slice(&mut self, buf: &[u8]) -> ::core::result::Result<usize, ::embedded_msgpack::decode::Error> {
+                #body
+            }
+        }
+    })
+}
@@ -0,0 +1,63 @@
+//! Reading and writing single messages with the async traits of `embedded-io-async`.
+
+use crate::{decode, encode};
+use embedded_io_async::{Read, ReadExactError, Write};
+
+/// Error type of [`write_message`] and [`read_message`].
+#[derive(Debug)]
+pub enum Error<E> {
+    /// Serializing the message failed.
+    Encode(encode::Error),
+    /// Deserializing the message failed. [`decode::Error::EndOfBuffer`] means that the reader ended before the
+    /// message was complete and [`decode::Error::OutOfBounds`] that the message does not fit into the buffer.
+    Decode(decode::Error),
+    /// The reader or writer failed.
+    Io(E),
+}
+
+impl<E: core::fmt::Debug> core::fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Encode(e) => write!(f, "{}", e),
+            Error::Decode(e) => write!(f, "{}", e),
+            Error::Io(e) => write!(f, "I/O error: {:?}", e),
+        }
+    }
+}
+
+/// Serializes `value` into `scratch` and writes it to `writer`. Returns the number of bytes written.
+///
+/// The writer is not flushed.
+pub async fn write_message<W: Write, T: serde::Serialize + ?Sized>(
+    writer: &mut W,
+    value: &T,
+    scratch: &mut [u8],
+) -> Result<usize, Error<W::Error>> {
+    let len = encode::serde::to_array(value, scratch).map_err(Error::Encode)?;
+    writer.write_all(&scratch[..len]).await.map_err(Error::Io)?;
+    Ok(len)
+}
+
+/// Reads one message from `reader` into `buf` and deserializes it.
+///
+/// The length of the message is determined while reading, so no bytes following it are consumed and the next
+/// message can be read with another call.
+pub async fn read_message<'a, R: Read, T: serde::Deserialize<'a>>(reader: &mut R, buf: &'a mut [u8]) -> Result<T, Error<R::Error>> {
+    let mut len = 0;
+    loop {
+        let needed = decode::min_message_len(&buf[..len]).map_err(Error::Decode)?;
+        if needed <= len {
+            break;
+        }
+        if needed > buf.len() {
+            return Err(Error::Decode(decode::Error::OutOfBounds));
+        }
+        reader.read_exact(&mut buf[len..needed]).await.map_err(|e| match e {
+            ReadExactError::UnexpectedEof => Error::Decode(decode::Error::EndOfBuffer),
+            ReadExactError::Other(e) => Error::Io(e),
+        })?;
+        len = needed;
+    }
+    let buf: &'a [u8] = buf;
+    decode::from_slice(&buf[..len]).map_err(Error::Decode)
+}
@@ -0,0 +1,165 @@
+use super::{read_array_len, read_bin, read_str, Error, MessageView};
+use crate::{encode::Binary, marker::Marker};
+use pastey::paste;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+extern crate alloc;
+
+/// Types that can be decoded without going through serde, possibly borrowing from the input.
+///
+/// Unlike [`DeserializeFromSlice`](super::DeserializeFromSlice) this creates a new value, so `&str`, `&[u8]`
+/// and other types referring to `buf` can be decoded without copying.
+///
+/// ```
+/// # use embedded_msgpack::decode::Decode;
+/// let buf = [0xa2, b'h', b'i', 0xc4, 0x01, 0xff];
+/// let (name, n) = <&str>::decode(&buf)?;
+/// let (data, _) = <&[u8]>::decode(&buf[n..])?;
+/// assert_eq!((name, data), ("hi", &[0xff][..]));
+/// # Ok::<(), embedded_msgpack::decode::Error>(())
+/// ```
+pub trait Decode<'a>: Sized {
+    /// Decodes the first value in `buf` and returns it together with the number of bytes read.
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error>;
+}
+
+macro_rules! decode_primitive {
+    ($ty:ident) => {
+        paste! {
+            impl<'a> Decode<'a> for $ty {
+                fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> { super::[<read_ $ty>](buf) }
+            }
+        }
+    };
+}
+macro_rules! decode_primitives {
+    ($($ty:ident),*) => { $( decode_primitive!($ty); )* };
+}
+
+decode_primitives!(bool, u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl<'a> Decode<'a> for &'a str {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> { read_str(buf) }
+}
+
+impl<'a> Decode<'a> for &'a [u8] {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> { read_bin(buf) }
+}
+
+impl<'a> Decode<'a> for Binary<'a> {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        let (data, n) = read_bin(buf)?;
+        Ok((Binary::new(data), n))
+    }
+}
+
+/// Nil decodes to `None`.
+impl<'a, T: Decode<'a>> Decode<'a> for Option<T> {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        if buf.first() == Some(&Marker::Null.to_u8()) {
+            return Ok((None, 1));
+        }
+        let (value, n) = T::decode(buf)?;
+        Ok((Some(value), n))
+    }
+}
+
+/// Fails with [`Error::OutOfBounds`] unless the array has exactly `N` elements.
+impl<'a, T: Decode<'a> + Default, const N: usize> Decode<'a> for [T; N] {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        let (len, mut pos) = read_array_len(buf)?;
+        if len != N {
+            return Err(Error::OutOfBounds);
+        }
+        let mut array = [(); N].map(|_| T::default());
+        for item in array.iter_mut() {
+            let (value, n) = T::decode(&buf[pos..])?;
+            *item = value;
+            pos += n;
+        }
+        Ok((array, pos))
+    }
+}
+
+/// Fails with [`Error::OutOfBounds`] if the array has more than `N` elements.
+#[cfg(feature = "heapless")]
+impl<'a, T: Decode<'a>, const N: usize> Decode<'a> for heapless::Vec<T, N> {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        let (len, mut pos) = read_array_len(buf)?;
+        if len > N {
+            return Err(Error::OutOfBounds);
+        }
+        let mut vec = heapless::Vec::new();
+        for _ in 0..len {
+            let (value, n) = T::decode(&buf[pos..])?;
+            // cannot fail, the length was checked above
+            let _ = vec.push(value);
+            pos += n;
+        }
+        Ok((vec, pos))
+    }
+}
+
+/// Fails with [`Error::OutOfBounds`] if the string is longer than `N` bytes.
+#[cfg(feature = "heapless")]
+impl<'a, const N: usize> Decode<'a> for heapless::String<N> {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        let (s, n) = read_str(buf)?;
+        let mut string = heapless::String::new();
+        string.push_str(s).map_err(|_| Error::OutOfBounds)?;
+        Ok((string, n))
+    }
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl<'a, T: Decode<'a>> Decode<'a> for alloc::vec::Vec<T> {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        let (len, mut pos) = read_array_len(buf)?;
+        // every element takes at least one byte
+        let mut vec = alloc::vec::Vec::with_capacity(len.min(buf.len() - pos));
+        for _ in 0..len {
+            let (value, n) = T::decode(&buf[pos..])?;
+            vec.push(value);
+            pos += n;
+        }
+        Ok((vec, pos))
+    }
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl<'a> Decode<'a> for alloc::string::String {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        let (s, n) = read_str(buf)?;
+        Ok((s.into(), n))
+    }
+}
+
+#[cfg(feature = "ext")]
+impl<'a> Decode<'a> for crate::Ext<'a> {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        let (header_len, data_len) = crate::ext::read_ext_len(buf)?;
+        Ok((crate::ext::try_deserialize_ext(buf)?, header_len + data_len))
+    }
+}
+
+#[cfg(feature = "timestamp")]
+impl<'a> Decode<'a> for crate::timestamp::Timestamp {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        use core::convert::TryInto;
+        let (ext, n) = crate::Ext::decode(buf)?;
+        Ok((ext.try_into()?, n))
+    }
+}
+
+impl<'a> Decode<'a> for MessageView<'a> {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> {
+        let view = MessageView::new(buf)?;
+        Ok((view, view.as_slice().len()))
+    }
+}
+
+/// Decodes with [`DEFAULT_MAX_DEPTH`](super::DEFAULT_MAX_DEPTH).
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl<'a> Decode<'a> for crate::Value<'a> {
+    fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> { crate::Value::decode(buf) }
+}
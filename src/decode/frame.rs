@@ -0,0 +1,166 @@
+use super::{
+    encoded_scalar_len, skip_any,
+    stream::{decode_token, token_len, Token},
+    Error,
+};
+use crate::marker::Marker;
+
+/// Returns the length of the first message in `buf`, or `None` if `buf` does not hold a complete message yet.
+///
+/// Malformed input is reported as an error, so it can be told apart from input that is only incomplete.
+///
+/// ```
+/// # use embedded_msgpack::decode::message_length;
+/// assert_eq!(message_length(&[0x92, 0x01])?, None);
+/// assert_eq!(message_length(&[0x92, 0x01, 0x02, 0x03])?, Some(3));
+/// assert!(message_length(&[0xc1]).is_err());
+/// # Ok::<(), embedded_msgpack::decode::Error>(())
+/// ```
+pub fn message_length(buf: &[u8]) -> Result<Option<usize>, Error> {
+    match skip_any(buf) {
+        Ok((_, 0)) | Err(Error::EndOfBuffer) => Ok(None),
+        Ok((_, n)) => Ok(Some(n)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns the length of the first message in `buf` if it is complete, or otherwise a lower bound for it that is
+/// larger than `buf.len()`.
+///
+/// Unlike [`message_length`] this tells how many bytes are missing at least, so a message can be read from a
+/// source without reading past its end.
+#[cfg_attr(not(feature = "embedded-io-async"), allow(dead_code))]
+pub(crate) fn min_message_len(buf: &[u8]) -> Result<usize, Error> {
+    let mut n = 0;
+    let mut pending: usize = 1;
+    while pending > 0 {
+        pending -= 1;
+        let rest = &buf[n..];
+        let len = match rest.first().map(|&marker| Marker::from_u8(marker)) {
+            None => Ok(1),
+            Some(Marker::FixArray(_) | Marker::Array16 | Marker::Array32 | Marker::FixMap(_) | Marker::Map16 | Marker::Map32) => {
+                token_len(rest).and_then(|header_len| {
+                    pending = match decode_token(&rest[..header_len])? {
+                        Token::ArrayStart(len) => pending.saturating_add(len),
+                        Token::MapStart(len) => pending.saturating_add(len.saturating_mul(2)),
+                        _ => unreachable!(),
+                    };
+                    Ok(header_len)
+                })
+            }
+            Some(_) => encoded_scalar_len(rest),
+        };
+        // every pending value takes at least one more byte
+        match len {
+            Ok(len) if len <= rest.len() => n += len,
+            Ok(len) => return Ok(n.checked_add(len).ok_or(Error::OutOfBounds)?.saturating_add(pending)),
+            Err(Error::EndOfBuffer) => return Ok((buf.len() + 1).saturating_add(pending)),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+/// Iterator over the complete messages in a buffer holding several messages back to back.
+///
+/// Iteration stops at the first incomplete message, which is left in [`Messages::remainder`], or after the first
+/// malformed one.
+#[derive(Clone)]
+pub struct Messages<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Messages<'a> {
+    pub const fn new(buf: &'a [u8]) -> Self { Messages { buf } }
+    /// Returns the bytes that were not returned as messages yet.
+    pub const fn remainder(&self) -> &'a [u8] { self.buf }
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Result<&'a [u8], Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match message_length(self.buf) {
+            Ok(Some(n)) => {
+                let (message, rest) = self.buf.split_at(n);
+                self.buf = rest;
+                Some(Ok(message))
+            }
+            Ok(None) => None,
+            Err(e) => {
+                self.buf = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Collects bytes received in arbitrary fragments, e.g. from a serial port, and splits them into messages.
+///
+/// ```
+/// # use embedded_msgpack::decode::Framer;
+/// let mut framer = Framer::<32>::new();
+/// framer.push(&[0x92, 0x01]);
+/// assert_eq!(framer.next_message()?, None);
+/// framer.push(&[0x02, 0xc3]);
+/// assert_eq!(framer.next_message()?, Some(&[0x92, 0x01, 0x02][..]));
+/// assert_eq!(framer.next_message()?, Some(&[0xc3][..]));
+/// # Ok::<(), embedded_msgpack::decode::Error>(())
+/// ```
+pub struct Framer<const N: usize> {
+    buf: [u8; N],
+    /// Number of valid bytes in `buf`
+    len: usize,
+    /// Number of bytes already returned as messages
+    consumed: usize,
+}
+
+impl<const N: usize> Framer<N> {
+    pub const fn new() -> Self {
+        Framer {
+            buf: [0; N],
+            len: 0,
+            consumed: 0,
+        }
+    }
+
+    /// Appends as much of `data` as fits and returns the number of bytes taken.
+    pub fn push(&mut self, data: &[u8]) -> usize {
+        self.buf.copy_within(self.consumed..self.len, 0);
+        self.len -= self.consumed;
+        self.consumed = 0;
+        let n = data.len().min(N - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&data[..n]);
+        self.len += n;
+        n
+    }
+
+    /// Returns the next complete message, or `None` if more bytes have to be pushed first.
+    ///
+    /// Fails if the buffered bytes are malformed and with [`Error::OutOfBounds`] if the message does not fit into the
+    /// `N` bytes of the buffer. In both cases the framer has to be [cleared](Framer::clear) to continue.
+    pub fn next_message(&mut self) -> Result<Option<&[u8]>, Error> {
+        match message_length(&self.buf[self.consumed..self.len])? {
+            Some(n) => {
+                let start = self.consumed;
+                self.consumed += n;
+                Ok(Some(&self.buf[start..self.consumed]))
+            }
+            None if self.consumed == 0 && self.len == N => Err(Error::OutOfBounds),
+            None => Ok(None),
+        }
+    }
+
+    /// Number of buffered bytes that were not returned as messages yet.
+    pub const fn len(&self) -> usize { self.len - self.consumed }
+    pub const fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Discards all buffered bytes.
+    pub const fn clear(&mut self) {
+        self.len = 0;
+        self.consumed = 0;
+    }
+}
+
+impl<const N: usize> Default for Framer<N> {
+    fn default() -> Self { Self::new() }
+}
@@ -1,11 +1,33 @@
+mod borrowed;
+mod frame;
+mod read;
 #[cfg(feature = "serde")]
 mod serde;
+mod stream;
+mod view;
+
+pub use borrowed::Decode;
+#[cfg(feature = "embedded-io-async")]
+pub(crate) use frame::min_message_len;
+pub use frame::{message_length, Framer, Messages};
+#[cfg(feature = "std")]
+pub use read::IoReader;
+pub use read::Read;
+pub use stream::{StreamDecoder, Token};
+
+#[cfg(feature = "derive")]
+pub use embedded_msgpack_derive::DeserializeFromSlice;
+pub use view::{Elements, Entries, MessageView};
 
 use crate::marker::Marker;
 
 use core::convert::{TryFrom, TryInto as _};
+use pastey::paste;
 use zerocopy::SplitByteSlice;
 
+#[cfg(any(feature = "alloc", feature = "std"))]
+extern crate alloc;
+
 /// Error type indicating why deserialization failed
 #[derive(Debug)]
 pub enum Error {
@@ -22,77 +44,319 @@ pub enum Error {
     CustomError,
     #[cfg(feature = "custom-error-messages")]
     CustomErrorWithMessage(heapless::String<64>),
-    NotAscii,
+    /// A string is not valid UTF-8.
+    InvalidUtf8,
     UnsupportedType,
     InvalidNewTypeLength,
+    /// A map did not contain the requested key.
+    KeyNotFound,
+    /// Arrays and maps are nested deeper than allowed. See [`DEFAULT_MAX_DEPTH`].
+    DepthLimitExceeded,
+    /// The source of a [`StreamDecoder`] has no more data before the current token is complete.
+    NeedMoreData,
+    /// The source of a [`StreamDecoder`] failed.
+    ReadFailed,
+    /// The input continues after the value. See [`from_slice_exact`].
+    TrailingBytes,
+}
+
+/// Maximum nesting depth of arrays and maps unless configured otherwise.
+///
+/// Deserializing nested values recurses, so this bounds the stack usage for untrusted input. [`MessageView::new`] uses
+/// it as well, while [`skip_any`] does not recurse and is not limited. See [`skip_any_with_max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// How the deserializer handles strings that are not valid UTF-8.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
+pub enum Utf8Mode {
+    /// Fail with [`Error::InvalidUtf8`].
+    Strict,
+    /// Replace invalid sequences with `U+FFFD`. Only available with `alloc`, as the string has to be copied.
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    Lossy,
+    /// Pass the raw bytes to the visitor like a binary.
+    Raw,
+}
+
+/// Options controlling how the deserializer decodes data.
+#[derive(Clone, Copy)]
+#[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
+pub struct Config {
+    utf8_mode: Utf8Mode,
+    max_depth: usize,
+}
+
+impl Config {
+    pub const fn new() -> Self {
+        Config {
+            utf8_mode: Utf8Mode::Strict,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+    /// Set how strings that are not valid UTF-8 are handled. Defaults to [`Utf8Mode::Strict`].
+    pub const fn with_utf8_mode(mut self, utf8_mode: Utf8Mode) -> Self {
+        self.utf8_mode = utf8_mode;
+        self
+    }
+    /// Set the maximum nesting depth of arrays and maps. Defaults to [`DEFAULT_MAX_DEPTH`].
+    pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self { Self::new() }
 }
 
 #[cfg(feature = "serde")]
 // #[inline(never)]
-pub fn from_slice<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8]) -> Result<T, Error> {
-    let mut de = serde::Deserializer::new(buf);
+pub fn from_slice<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8]) -> Result<T, Error> { from_slice_with_config(buf, Config::new()) }
+
+#[cfg(feature = "serde")]
+pub fn from_slice_with_config<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8], config: Config) -> Result<T, Error> {
+    Ok(from_slice_partial_with_config(buf, config)?.0)
+}
+
+/// Deserializes the first value in `buf` and returns it together with the number of bytes read.
+#[cfg(feature = "serde")]
+pub fn from_slice_partial<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8]) -> Result<(T, usize), Error> {
+    from_slice_partial_with_config(buf, Config::new())
+}
+
+#[cfg(feature = "serde")]
+pub fn from_slice_partial_with_config<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8], config: Config) -> Result<(T, usize), Error> {
+    let mut de = serde::Deserializer::with_config(buf, config);
     let value = ::serde::de::Deserialize::deserialize(&mut de)?;
+    Ok((value, de.position()))
+}
+
+/// Deserializes a value like [`from_slice`], but fails with [`Error::TrailingBytes`] if `buf` holds more than
+/// that value.
+#[cfg(feature = "serde")]
+pub fn from_slice_exact<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8]) -> Result<T, Error> {
+    from_slice_exact_with_config(buf, Config::new())
+}
 
+#[cfg(feature = "serde")]
+pub fn from_slice_exact_with_config<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8], config: Config) -> Result<T, Error> {
+    let (value, len) = from_slice_partial_with_config(buf, config)?;
+    if len != buf.len() {
+        return Err(Error::TrailingBytes);
+    }
     Ok(value)
 }
 
-pub trait DeserializeFromSlice {
-    fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error>;
+/// Returns an iterator deserializing the messages in `buf`, which are written back to back without any framing.
+///
+/// ```
+/// # use embedded_msgpack::decode::iter_from_slice;
+/// let mut iter = iter_from_slice::<u16>(&[0x01, 0xcd, 0x01, 0x00]);
+/// assert_eq!(iter.next().unwrap()?, 1);
+/// assert_eq!(iter.next().unwrap()?, 256);
+/// assert!(iter.next().is_none());
+/// # Ok::<(), embedded_msgpack::decode::Error>(())
+/// ```
+#[cfg(feature = "serde")]
+pub fn iter_from_slice<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8]) -> IterFromSlice<'a, T> {
+    iter_from_slice_with_config(buf, Config::new())
 }
 
-impl DeserializeFromSlice for Option<u8> {
-    fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let (v, n) = read_u8(buf)?;
-        *self = Some(v);
-        Ok(n)
+#[cfg(feature = "serde")]
+pub fn iter_from_slice_with_config<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8], config: Config) -> IterFromSlice<'a, T> {
+    IterFromSlice {
+        buf,
+        config,
+        _type: core::marker::PhantomData,
     }
 }
-impl DeserializeFromSlice for Option<u16> {
-    fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let (v, n) = read_u16(buf)?;
-        *self = Some(v);
-        Ok(n)
+
+/// Iterator over the messages in a buffer. See [`iter_from_slice`].
+///
+/// Iteration ends when the buffer is used up or after the first error. A value that is deserialized without
+/// consuming any bytes is reported as [`Error::InvalidType`], because the iterator would not advance otherwise.
+#[cfg(feature = "serde")]
+pub struct IterFromSlice<'a, T> {
+    buf: &'a [u8],
+    config: Config,
+    _type: core::marker::PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a, T> IterFromSlice<'a, T> {
+    /// Returns the bytes that were not deserialized yet.
+    pub const fn remainder(&self) -> &'a [u8] { self.buf }
+}
+
+#[cfg(feature = "serde")]
+impl<'a, T: ::serde::de::Deserialize<'a>> Iterator for IterFromSlice<'a, T> {
+    type Item = Result<T, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        match from_slice_partial_with_config(self.buf, self.config) {
+            Ok((_, 0)) => {
+                self.buf = &[];
+                Some(Err(Error::InvalidType))
+            }
+            Ok((value, len)) => {
+                self.buf = &self.buf[len..];
+                Some(Ok(value))
+            }
+            Err(e) => {
+                self.buf = &[];
+                Some(Err(e))
+            }
+        }
     }
 }
-impl DeserializeFromSlice for Option<u32> {
+
+/// Deserializes a value like [`from_slice_with_config`], but reports where decoding failed.
+#[cfg(feature = "error-context")]
+// Only large together with `custom-error-messages`. Boxing the error would require `alloc`.
+#[allow(clippy::result_large_err)]
+pub fn from_slice_with_context<'a, T: ::serde::de::Deserialize<'a>>(buf: &'a [u8], config: Config) -> Result<T, ContextError> {
+    let mut de = serde::Deserializer::with_config(buf, config);
+    ::serde::de::Deserialize::deserialize(&mut de).map_err(|e| de.context_error(e))
+}
+
+/// An [`Error`] together with the position in the input where it occurred.
+#[cfg(feature = "error-context")]
+#[derive(Debug)]
+pub struct ContextError {
+    pub(crate) error: Error,
+    pub(crate) offset: usize,
+    pub(crate) marker: Option<u8>,
+    pub(crate) expected: Option<&'static str>,
+    pub(crate) field: Option<&'static str>,
+}
+
+#[cfg(feature = "error-context")]
+impl ContextError {
+    pub const fn error(&self) -> &Error { &self.error }
+    pub fn into_error(self) -> Error { self.error }
+    /// Byte offset of the value that could not be decoded.
+    pub const fn offset(&self) -> usize { self.offset }
+    /// Marker byte found at [`ContextError::offset`], or `None` if the input ended there.
+    pub const fn marker(&self) -> Option<u8> { self.marker }
+    /// Kind of value the deserializer asked for, e.g. `"u16"` or `"struct"`.
+    pub const fn expected(&self) -> Option<&'static str> { self.expected }
+    /// Name of the innermost struct field being decoded, if known.
+    pub const fn field(&self) -> Option<&'static str> { self.field }
+}
+
+#[cfg(feature = "error-context")]
+impl From<ContextError> for Error {
+    fn from(e: ContextError) -> Self { e.error }
+}
+
+/// Types that can be decoded from MessagePack without going through serde.
+///
+/// Values are decoded in place, so types that borrow from the input like `&str` cannot implement it.
+pub trait DeserializeFromSlice {
+    /// Decodes the first value in `buf` into `self` and returns the number of bytes read.
+    fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error>;
+}
+
+macro_rules! deserialize_from_slice_primitive {
+    ($ty:ident) => {
+        paste! {
+            impl DeserializeFromSlice for $ty {
+                fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                    let (v, n) = [<read_ $ty>](buf)?;
+                    *self = v;
+                    Ok(n)
+                }
+            }
+        }
+    };
+}
+macro_rules! deserialize_from_slice_primitives {
+    ($($ty:ident),*) => { $( deserialize_from_slice_primitive!($ty); )* };
+}
+
+deserialize_from_slice_primitives!(bool, u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// Nil decodes to `None`, everything else is decoded into a default `T`.
+impl<T: DeserializeFromSlice + Default> DeserializeFromSlice for Option<T> {
     fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let (v, n) = read_u32(buf)?;
-        *self = Some(v);
+        if buf.first() == Some(&Marker::Null.to_u8()) {
+            *self = None;
+            return Ok(1);
+        }
+        let mut value = T::default();
+        let n = value.from_slice(buf)?;
+        *self = Some(value);
         Ok(n)
     }
 }
-impl DeserializeFromSlice for Option<u64> {
+
+/// Fails with [`Error::OutOfBounds`] unless the array has exactly `N` elements.
+impl<T: DeserializeFromSlice, const N: usize> DeserializeFromSlice for [T; N] {
     fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let (v, n) = read_u64(buf)?;
-        *self = Some(v);
-        Ok(n)
+        let (len, mut pos) = read_array_len(buf)?;
+        if len != N {
+            return Err(Error::OutOfBounds);
+        }
+        for item in self.iter_mut() {
+            pos += item.from_slice(&buf[pos..])?;
+        }
+        Ok(pos)
     }
 }
-impl DeserializeFromSlice for Option<i8> {
+
+/// Fails with [`Error::OutOfBounds`] if the array has more than `N` elements.
+#[cfg(feature = "heapless")]
+impl<T: DeserializeFromSlice + Default, const N: usize> DeserializeFromSlice for heapless::Vec<T, N> {
     fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let (v, n) = read_i8(buf)?;
-        *self = Some(v);
-        Ok(n)
+        let (len, mut pos) = read_array_len(buf)?;
+        if len > N {
+            return Err(Error::OutOfBounds);
+        }
+        self.clear();
+        for _ in 0..len {
+            let mut item = T::default();
+            pos += item.from_slice(&buf[pos..])?;
+            // cannot fail, the length was checked above
+            let _ = self.push(item);
+        }
+        Ok(pos)
     }
 }
-impl DeserializeFromSlice for Option<i16> {
+
+/// Fails with [`Error::OutOfBounds`] if the string is longer than `N` bytes.
+#[cfg(feature = "heapless")]
+impl<const N: usize> DeserializeFromSlice for heapless::String<N> {
     fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let (v, n) = read_i16(buf)?;
-        *self = Some(v);
+        let (s, n) = read_str(buf)?;
+        self.clear();
+        self.push_str(s).map_err(|_| Error::OutOfBounds)?;
         Ok(n)
     }
 }
-impl DeserializeFromSlice for Option<i32> {
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl<T: DeserializeFromSlice + Default> DeserializeFromSlice for alloc::vec::Vec<T> {
     fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let (v, n) = read_i32(buf)?;
-        *self = Some(v);
-        Ok(n)
+        let (len, mut pos) = read_array_len(buf)?;
+        self.clear();
+        for _ in 0..len {
+            let mut item = T::default();
+            pos += item.from_slice(&buf[pos..])?;
+            self.push(item);
+        }
+        Ok(pos)
     }
 }
-impl DeserializeFromSlice for Option<i64> {
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl DeserializeFromSlice for alloc::string::String {
     fn from_slice(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let (v, n) = read_i64(buf)?;
-        *self = Some(v);
+        let (s, n) = read_str(buf)?;
+        self.clear();
+        self.push_str(s);
         Ok(n)
     }
 }
@@ -151,9 +415,10 @@ pub fn read_sint<B: SplitByteSlice, T: TryFrom<i64>>(buf: B) -> Result<(T, usize
 #[cfg(not(feature = "i64"))]
 #[inline(always)]
 pub fn read_sint<B: SplitByteSlice, T: TryFrom<i32>>(buf: B) -> Result<(T, usize), Error> {
-    match read_i32(buf) {
+    // `read_i64` rejects the 64 bit markers without the `i64` feature
+    match read_i64(buf) {
         Ok((v, len)) => {
-            if let Ok(v) = T::try_from(v) {
+            if let Some(v) = i32::try_from(v).ok().and_then(|v| T::try_from(v).ok()) {
                 Ok((v, len))
             } else {
                 Err(Error::OutOfBounds)
@@ -501,7 +766,7 @@ pub fn read_str(buf: &[u8]) -> Result<(&str, usize), Error> {
         // This is safe because all ASCII characters are valid UTF-8 characters
         unsafe { core::str::from_utf8_unchecked(buf) }
     } else {
-        return Err(Error::NotAscii);
+        core::str::from_utf8(buf).map_err(|_| Error::InvalidUtf8)?
     };
     Ok((s, header_len + len))
 }
@@ -601,12 +866,92 @@ pub fn read_map_len<B: SplitByteSlice>(buf: B) -> Result<(usize, usize), Error>
     }
 }
 
+/// Skips the first value in `buf` and returns its length.
+///
+/// Nested arrays and maps are skipped by counting the elements that are still pending instead of recursing, so
+/// the stack usage does not depend on the shape of the message.
 pub fn skip_any<B: SplitByteSlice>(buf: B) -> Result<((), usize), Error> {
     if buf.is_empty() {
         return Ok(((), 0));
     }
-    let marker = Marker::from_u8(buf[0]);
-    let n = match marker {
+    let mut n = 0;
+    let mut pending: usize = 1;
+    while pending > 0 {
+        pending -= 1;
+        let rest = &buf[n..];
+        n += match Marker::from_u8(*rest.first().ok_or(Error::EndOfBuffer)?) {
+            Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => {
+                let (len, header_len) = read_array_len(rest)?;
+                pending = pending.saturating_add(len);
+                header_len
+            }
+            Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+                let (len, header_len) = read_map_len(rest)?;
+                pending = pending.saturating_add(len.saturating_mul(2));
+                header_len
+            }
+            _ => scalar_len(rest)?,
+        };
+    }
+    Ok(((), n))
+}
+
+/// Like [`skip_any`], but fails with [`Error::DepthLimitExceeded`] if arrays and maps are nested deeper than
+/// `MAX_DEPTH`.
+///
+/// This does not recurse either. The pending element counts of the enclosing arrays and maps are kept in an array of
+/// `MAX_DEPTH` counters, so the stack usage only depends on the limit.
+pub fn skip_any_with_max_depth<const MAX_DEPTH: usize>(buf: &[u8]) -> Result<((), usize), Error> {
+    if buf.is_empty() {
+        return Ok(((), 0));
+    }
+    let mut outer = [0usize; MAX_DEPTH];
+    let mut depth = 0;
+    let mut n = 0;
+    let mut pending: usize = 1;
+    loop {
+        while pending == 0 {
+            if depth == 0 {
+                return Ok(((), n));
+            }
+            depth -= 1;
+            pending = outer[depth];
+        }
+        pending -= 1;
+        let rest = &buf[n..];
+        let (len, header_len) = match Marker::from_u8(*rest.first().ok_or(Error::EndOfBuffer)?) {
+            Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => read_array_len(rest)?,
+            Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+                let (len, header_len) = read_map_len(rest)?;
+                (len.saturating_mul(2), header_len)
+            }
+            _ => {
+                n += scalar_len(rest)?;
+                continue;
+            }
+        };
+        if depth == MAX_DEPTH {
+            return Err(Error::DepthLimitExceeded);
+        }
+        outer[depth] = pending;
+        depth += 1;
+        pending = len;
+        n += header_len;
+    }
+}
+
+/// Returns the encoded length of the scalar (anything but an array or map) at the start of `buf`.
+fn scalar_len(buf: &[u8]) -> Result<usize, Error> {
+    let n = encoded_scalar_len(buf)?;
+    if buf.len() < n {
+        return Err(Error::EndOfBuffer);
+    }
+    Ok(n)
+}
+
+/// Returns the encoded length of the scalar at the start of `buf` as given by its header, even if `buf` ends earlier.
+pub(crate) fn encoded_scalar_len(buf: &[u8]) -> Result<usize, Error> {
+    Ok(match Marker::from_u8(buf[0]) {
         Marker::FixPos(_) => 1,
         Marker::U8 => 2,
         Marker::U16 => 3,
@@ -621,7 +966,9 @@ pub fn skip_any<B: SplitByteSlice>(buf: B) -> Result<((), usize), Error> {
         Marker::F32 => 5,
         Marker::F64 => 9,
 
-        Marker::Null | Marker::True | Marker::False | Marker::Reserved => 1,
+        Marker::Null | Marker::True | Marker::False => 1,
+        // never used, so this cannot be the start of a value
+        Marker::Reserved => return Err(Error::InvalidType),
 
         Marker::FixStr(n) => n as usize + 1,
         Marker::Str8 | Marker::Bin8 => {
@@ -640,26 +987,12 @@ pub fn skip_any<B: SplitByteSlice>(buf: B) -> Result<((), usize), Error> {
             if buf.len() < 5 {
                 return Err(Error::EndOfBuffer);
             }
-            5 + read_be_u32(&buf[1..5]) as usize
+            // the length can overflow on 32 bit targets
+            (read_be_u32(&buf[1..5]) as usize).checked_add(5).ok_or(Error::OutOfBounds)?
         }
 
-        Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => {
-            let (len, n) = read_array_len(&buf[..])?;
-            let mut n = n;
-            for _ in 0..len {
-                //TODO: May overflow stack on embedded systems. Maybe add some kind of safeguard to limit recursion depth
-                n += skip_any(&buf[n..])?.1;
-            }
-            n
-        }
-        Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
-            let (len, n) = read_map_len(&buf[..])?;
-            let mut n = n;
-            for _ in 0..len * 2 {
-                //TODO: May overflow stack on embedded systems. Maybe add some kind of safeguard to limit recursion depth
-                n += skip_any(&buf[n..])?.1;
-            }
-            n
+        Marker::FixArray(_) | Marker::Array16 | Marker::Array32 | Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+            return Err(Error::InvalidType)
         }
         Marker::FixExt1 => 3,
         Marker::FixExt2 => 4,
@@ -682,11 +1015,7 @@ pub fn skip_any<B: SplitByteSlice>(buf: B) -> Result<((), usize), Error> {
             if buf.len() < 5 {
                 return Err(Error::EndOfBuffer);
             }
-            6 + read_be_u32(&buf[1..5]) as usize
+            (read_be_u32(&buf[1..5]) as usize).checked_add(6).ok_or(Error::OutOfBounds)?
         }
-    };
-    if buf.len() < n {
-        return Err(Error::EndOfBuffer);
-    }
-    Ok(((), n))
+    })
 }
@@ -0,0 +1,59 @@
+use super::Error;
+
+/// Byte source the [`StreamDecoder`](super::StreamDecoder) reads from.
+///
+/// Implemented for `&[u8]` (the slice is advanced past the read bytes), `heapless::Deque<u8, N>` and, via
+/// [`IoReader`], any `std::io::Read`.
+pub trait Read {
+    /// Reads up to `buf.len()` bytes into `buf` and returns how many were read.
+    ///
+    /// Returning `0` means that no data is available right now, not necessarily that the source has ended.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> { (**self).read(buf) }
+}
+
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let n = buf.len().min(self.len());
+        let (head, tail) = self.split_at(n);
+        buf[..n].copy_from_slice(head);
+        *self = tail;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Read for heapless::Deque<u8, N> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.pop_front() {
+                Some(byte) => buf[n] = byte,
+                None => break,
+            }
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+/// Adapter implementing [`Read`] for any `std::io::Read`.
+///
+/// `WouldBlock` and `Interrupted` are reported as no data being available.
+#[cfg(feature = "std")]
+pub struct IoReader<R>(pub R);
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> Read for IoReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match self.0.read(buf) {
+            Ok(n) => Ok(n),
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted) => Ok(0),
+            Err(_) => Err(Error::ReadFailed),
+        }
+    }
+}
@@ -1,43 +1,54 @@
 use serde::de;
 
 use super::{print_debug, Deserializer, Error};
+use crate::marker::Marker;
 
-pub(crate) struct UnitVariantAccess<'a, 'b> {
+pub(crate) struct VariantAccess<'a, 'b> {
     de: &'a mut Deserializer<'b>,
+    /// `true` if the variant is wrapped in a single-entry map (`{"Variant": payload}`)
+    wrapped: bool,
 }
 
-impl<'a, 'b> UnitVariantAccess<'a, 'b> {
-    pub(crate) fn new(de: &'a mut Deserializer<'b>) -> Self { UnitVariantAccess { de } }
+impl<'a, 'b> VariantAccess<'a, 'b> {
+    pub(crate) fn new(de: &'a mut Deserializer<'b>, wrapped: bool) -> Self { VariantAccess { de, wrapped } }
 }
 
-impl<'a, 'de> de::EnumAccess<'de> for UnitVariantAccess<'a, 'de> {
+impl<'a, 'de> de::EnumAccess<'de> for VariantAccess<'a, 'de> {
     type Error = Error;
     type Variant = Self;
 
     fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Self::Error>
     where V: de::DeserializeSeed<'de> {
-        print_debug::<V>("UnitVariantAccess::", "variant_seed", &self.de);
+        print_debug::<V>("VariantAccess::", "variant_seed", &self.de);
         let variant = seed.deserialize(&mut *self.de)?;
         Ok((variant, self))
     }
 }
 
-impl<'de, 'a> de::VariantAccess<'de> for UnitVariantAccess<'a, 'de> {
+impl<'de, 'a> de::VariantAccess<'de> for VariantAccess<'a, 'de> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<(), Self::Error> {
-        print_debug::<()>("UnitVariantAccess::", "unit_variant", &self.de);
-        Ok(())
+        print_debug::<()>("VariantAccess::", "unit_variant", &self.de);
+        if self.wrapped {
+            // `{"Variant": nil}` or `{"Variant": []}`
+            de::Deserialize::deserialize(&mut *self.de)
+        } else {
+            Ok(())
+        }
     }
 
     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
     where T: de::DeserializeSeed<'de> {
-        print_debug::<T>("UnitVariantAccess::", "newtype_variant_seed", &self.de);
+        print_debug::<T>("VariantAccess::", "newtype_variant_seed", &self.de);
+        if self.wrapped {
+            return seed.deserialize(self.de);
+        }
 
         let (len, header_len) = crate::decode::read_array_len(&self.de.slice[self.de.index..])?;
         self.de.index += header_len;
         match len {
-            1 => seed.deserialize(self.de),
+            1 => self.de.nested(|de| seed.deserialize(de)),
             0 => Err(Error::InvalidNewTypeLength),
             _ => Err(Error::InvalidNewTypeLength),
         }
@@ -45,23 +56,23 @@ impl<'de, 'a> de::VariantAccess<'de> for UnitVariantAccess<'a, 'de> {
 
     fn tuple_variant<V>(self, v_len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where V: de::Visitor<'de> {
-        print_debug::<V>("UnitVariantAccess::", "tuple_variant", &self.de);
+        print_debug::<V>("VariantAccess::", "tuple_variant", &self.de);
         let (len, header_len) = crate::decode::read_array_len(&self.de.slice[self.de.index..])?;
         if len != v_len {
             return Err(Error::OutOfBounds);
         }
         self.de.index += header_len;
-        visitor.visit_seq(super::SeqAccess::new(&mut *self.de, len))
+        let outer = self.de.enter_fields(&[]);
+        let result = self.de.nested(|de| visitor.visit_seq(super::SeqAccess::new(de, len)));
+        self.de.leave_fields(outer, result)
     }
 
     fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
     where V: de::Visitor<'de> {
-        print_debug::<V>("UnitVariantAccess::", "struct_variant", &self.de);
-        let (len, header_len) = crate::decode::read_map_len(&self.de.slice[self.de.index..])?;
-        self.de.index += header_len;
-        if len != fields.len() {
-            return Err(Error::OutOfBounds);
+        print_debug::<V>("VariantAccess::", "struct_variant", &self.de);
+        if let Some(Marker::FixArray(_) | Marker::Array16 | Marker::Array32) = self.de.peek() {
+            return self.de.deserialize_struct_array(fields, visitor);
         }
-        visitor.visit_map(super::MapAccess::new(&mut *self.de, fields.len()))
+        self.de.deserialize_struct_map(fields, visitor)
     }
 }
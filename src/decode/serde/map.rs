@@ -19,6 +19,7 @@ impl<'a, 'de> de::MapAccess<'de> for MapAccess<'a, 'de> {
         print_debug::<K>("MapAccess::", "next_key_seed", &self.de);
         if self.count > 0 {
             self.count -= 1;
+            self.de.record_field_key();
             Ok(Some(seed.deserialize(&mut *self.de)?))
         } else {
             Ok(None)
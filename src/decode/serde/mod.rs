@@ -3,13 +3,18 @@ use core::fmt;
 use pastey::paste;
 use serde::de::{self, Visitor};
 
-use self::{enum_::UnitVariantAccess, map::MapAccess, seq::SeqAccess};
+#[cfg(feature = "ext")]
+use self::seq::ExtAccess;
+use self::{enum_::VariantAccess, map::MapAccess, seq::SeqAccess};
 
 mod enum_;
 mod map;
 mod seq;
 
-use super::Error;
+use super::{Config, Error, Utf8Mode};
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+extern crate alloc;
 
 type Result<T> = core::result::Result<T, Error>;
 
@@ -51,6 +56,21 @@ pub(crate) struct Deserializer<'b> {
     slice: &'b [u8],
     index: usize,
     state: State,
+    config: Config,
+    /// Number of arrays and maps currently entered
+    depth: usize,
+    #[cfg(feature = "error-context")]
+    context: Context,
+}
+
+/// What the deserializer was doing, reported in [`super::ContextError`].
+#[cfg(feature = "error-context")]
+#[derive(Clone, Copy)]
+struct Context {
+    expected: Option<&'static str>,
+    /// Fields of the innermost struct being deserialized
+    fields: &'static [&'static str],
+    field: Option<&'static str>,
 }
 
 enum State {
@@ -59,16 +79,133 @@ enum State {
 }
 
 impl<'a> Deserializer<'a> {
-    pub const fn new(slice: &'a [u8]) -> Deserializer<'a> {
+    pub const fn with_config(slice: &'a [u8], config: Config) -> Deserializer<'a> {
         Deserializer {
             slice,
             index: 0,
             state: State::Normal,
+            config,
+            depth: 0,
+            #[cfg(feature = "error-context")]
+            context: Context {
+                expected: None,
+                fields: &[],
+                field: None,
+            },
         }
     }
 
+    /// Number of bytes consumed so far.
+    pub(crate) const fn position(&self) -> usize { self.index }
+
+    #[inline(always)]
+    #[cfg_attr(not(feature = "error-context"), allow(unused_variables))]
+    const fn expecting(&mut self, kind: &'static str) {
+        #[cfg(feature = "error-context")]
+        {
+            self.context.expected = Some(kind);
+        }
+    }
+
+    /// Makes `fields` the struct fields that map keys and array positions refer to and returns the previous ones.
+    #[inline(always)]
+    #[cfg_attr(not(feature = "error-context"), allow(unused_variables))]
+    pub(crate) const fn enter_fields(&mut self, fields: &'static [&'static str]) -> &'static [&'static str] {
+        #[cfg(feature = "error-context")]
+        {
+            core::mem::replace(&mut self.context.fields, fields)
+        }
+        #[cfg(not(feature = "error-context"))]
+        {
+            &[]
+        }
+    }
+    /// Restores the fields returned by [`Deserializer::enter_fields`] once the value was deserialized successfully.
+    /// On errors they are kept, so the innermost field is reported.
+    #[inline(always)]
+    #[cfg_attr(not(feature = "error-context"), allow(unused_variables))]
+    pub(crate) const fn leave_fields<T>(&mut self, outer: &'static [&'static str], result: Result<T>) -> Result<T> {
+        #[cfg(feature = "error-context")]
+        if result.is_ok() {
+            self.context.fields = outer;
+        }
+        result
+    }
+    /// Records the struct field whose key is at the current position.
+    #[inline(always)]
+    #[cfg_attr(not(feature = "error-context"), allow(clippy::missing_const_for_fn))]
+    pub(crate) fn record_field_key(&mut self) {
+        #[cfg(feature = "error-context")]
+        if !self.context.fields.is_empty() {
+            if let Ok((key, _)) = super::read_str(&self.slice[self.index..]) {
+                self.context.field = self.context.fields.iter().find(|f| **f == key).copied();
+            }
+        }
+    }
+    /// Records the struct field at position `index` of an array encoded struct.
+    #[inline(always)]
+    #[cfg_attr(not(feature = "error-context"), allow(unused_variables, clippy::missing_const_for_fn))]
+    pub(crate) fn record_field_index(&mut self, index: usize) {
+        #[cfg(feature = "error-context")]
+        if !self.context.fields.is_empty() {
+            self.context.field = self.context.fields.get(index).copied();
+        }
+    }
+    #[cfg(feature = "error-context")]
+    pub(crate) fn context_error(&self, error: Error) -> super::ContextError {
+        super::ContextError {
+            error,
+            offset: self.index,
+            marker: self.slice.get(self.index).copied(),
+            expected: self.context.expected,
+            field: self.context.field,
+        }
+    }
+
+    /// Runs `f` one nesting level deeper, failing if that exceeds the configured maximum depth.
+    pub(crate) fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= self.config.max_depth {
+            return Err(Error::DepthLimitExceeded);
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Skips the next value.
+    pub(crate) fn skip_any(&mut self) -> Result<()> {
+        let (_, n) = super::skip_any(&self.slice[self.index..])?;
+        self.index += n;
+        Ok(())
+    }
+
     fn eat_byte(&mut self) { self.index += 1; }
 
+    /// Deserializes a struct encoded as an array of its field values.
+    ///
+    /// Elements beyond the fields known to the visitor are skipped, so fields can be appended to a struct without
+    /// breaking older readers.
+    pub(crate) fn deserialize_struct_array<V: Visitor<'a>>(&mut self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        let (len, header_len) = crate::decode::read_array_len(&self.slice[self.index..])?;
+        self.index += header_len;
+        let outer = self.enter_fields(fields);
+        let result = self.nested(|de| {
+            let mut seq = SeqAccess::new(de, len);
+            let value = visitor.visit_seq(&mut seq)?;
+            seq.skip_remaining()?;
+            Ok(value)
+        });
+        self.leave_fields(outer, result)
+    }
+    /// Deserializes a struct encoded as a map from field names to values.
+    pub(crate) fn deserialize_struct_map<V: Visitor<'a>>(&mut self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        let (len, header_len) = crate::decode::read_map_len(&self.slice[self.index..])?;
+        self.index += header_len;
+        let outer = self.enter_fields(fields);
+        let result = self.nested(|de| visitor.visit_map(MapAccess::new(de, len)));
+        self.leave_fields(outer, result)
+    }
     fn peek(&mut self) -> Option<Marker> { Some(Marker::from_u8(*self.slice.get(self.index)?)) }
 }
 
@@ -81,6 +218,7 @@ macro_rules! deserialize_primitive {
             fn [<deserialize_ $ty>]<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value>
         {
             print_debug::<V>("Deserializer::deserialize_", stringify!($ty), &self);
+            self.expecting(stringify!($ty));
             let (value, len) = paste! { super::[<read_ $ty>](&self.slice[self.index..])? };
             self.index += len;
             print_debug_value::<$ty, $ty>(stringify!(concat_idents!(Deserializer::deserialize_, $ty)), &self, &value);
@@ -100,6 +238,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
         print_debug::<V>("Deserializer::deserialize_", "i8", &self);
+        self.expecting("i8");
         let (value, len) = match self.state {
             State::Normal => super::read_i8(&self.slice[self.index..])?,
             // read the ext type as raw byte and not encoded as a normal i8
@@ -113,13 +252,28 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "str", &self);
-        let (s, len) = super::read_str(&self.slice[self.index..])?;
-        self.index += len;
-        visitor.visit_borrowed_str(s)
+        self.expecting("str");
+        match super::read_str(&self.slice[self.index..]) {
+            Ok((s, len)) => {
+                self.index += len;
+                visitor.visit_borrowed_str(s)
+            }
+            Err(Error::InvalidUtf8) if self.config.utf8_mode != Utf8Mode::Strict => {
+                let (bytes, len) = super::read_bin(&self.slice[self.index..])?;
+                self.index += len;
+                match self.config.utf8_mode {
+                    #[cfg(any(feature = "alloc", feature = "std"))]
+                    Utf8Mode::Lossy => visitor.visit_string(alloc::string::String::from_utf8_lossy(bytes).into_owned()),
+                    _ => visitor.visit_borrowed_bytes(bytes),
+                }
+            }
+            Err(e) => Err(e),
+        }
     }
 
     fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "bytes", &self);
+        self.expecting("bytes");
         let (value, len) = match self.state {
             State::Normal => super::read_bin(&self.slice[self.index..])?,
             // read the ext type as raw byte and not encoded as a normal i8
@@ -135,11 +289,13 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "byte_buf", &self);
+        self.expecting("byte_buf");
         self.deserialize_bytes(visitor)
     }
 
     fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "option", &self);
+        self.expecting("option");
         let marker = self.peek().ok_or(Error::EndOfBuffer)?;
         match marker {
             Marker::Null => {
@@ -152,30 +308,45 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "seq", &self);
+        self.expecting("seq");
         let (len, header_len) = crate::decode::read_array_len(&self.slice[self.index..])?;
         self.index += header_len;
-        visitor.visit_seq(SeqAccess::new(self, len))
+        let outer = self.enter_fields(&[]);
+        let result = self.nested(|de| {
+            let mut seq = SeqAccess::new(de, len);
+            let value = visitor.visit_seq(&mut seq)?;
+            // tuples may not read all elements
+            seq.skip_remaining()?;
+            Ok(value)
+        });
+        self.leave_fields(outer, result)
     }
 
     fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "tuple", &self);
+        self.expecting("tuple");
         self.deserialize_seq(visitor)
     }
 
     fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "tuple_struct", &self);
+        self.expecting("tuple_struct");
         self.deserialize_seq(visitor)
     }
 
     fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "map", &self);
+        self.expecting("map");
         let (len, header_len) = crate::decode::read_map_len(&self.slice[self.index..])?;
         self.index += header_len;
-        visitor.visit_map(MapAccess::new(self, len))
+        let outer = self.enter_fields(&[]);
+        let result = self.nested(|de| visitor.visit_map(MapAccess::new(de, len)));
+        self.leave_fields(outer, result)
     }
 
-    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "struct", &self);
+        self.expecting("struct");
         match name {
             #[cfg(feature = "ext")]
             crate::ext::TYPE_NAME | crate::timestamp::TYPE_NAME => {
@@ -200,37 +371,100 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                     Err(Error::EndOfBuffer)
                 }
             }
-            _ => self.deserialize_map(visitor),
+            _ => match self.peek().ok_or(Error::EndOfBuffer)? {
+                Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => self.deserialize_struct_array(fields, visitor),
+                _ => self.deserialize_struct_map(fields, visitor),
+            },
         }
     }
 
     fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "enum", &self);
-        visitor.visit_enum(UnitVariantAccess::new(self))
+        self.expecting("enum");
+        match self.peek().ok_or(Error::EndOfBuffer)? {
+            // `{"Variant": payload}`
+            Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+                let (len, header_len) = crate::decode::read_map_len(&self.slice[self.index..])?;
+                if len != 1 {
+                    return Err(Error::InvalidType);
+                }
+                self.index += header_len;
+                self.nested(|de| visitor.visit_enum(VariantAccess::new(de, true)))
+            }
+            // `"Variant"` or variant index, optionally followed by the payload
+            _ => visitor.visit_enum(VariantAccess::new(self, false)),
+        }
     }
 
     fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "identifier", &self);
-        self.deserialize_str(visitor)
+        self.expecting("identifier");
+        match self.peek().ok_or(Error::EndOfBuffer)? {
+            // enum variants encoded by index
+            Marker::FixPos(_) | Marker::U8 | Marker::U16 | Marker::U32 | Marker::U64 => self.deserialize_u64(visitor),
+            _ => self.deserialize_str(visitor),
+        }
     }
 
-    /// Unsupported. Can’t parse a value without knowing its expected type.
+    /// Deserializes whatever the next marker announces: integers, floats, nil, booleans, strings, binaries, arrays
+    /// and maps, and with the `ext` feature extension types as a sequence of their type and data. Other markers, like
+    /// the reserved `0xc1`, fail with [`Error::InvalidType`].
     fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "any", &self);
-        let (_, n) = super::skip_any(&self.slice[self.index..])?;
-        self.index += n;
-        visitor.visit_unit()
+        self.expecting("any");
+        match self.peek().ok_or(Error::EndOfBuffer)? {
+            Marker::FixPos(_) | Marker::U8 => self.deserialize_u8(visitor),
+            Marker::U16 => self.deserialize_u16(visitor),
+            Marker::U32 => self.deserialize_u32(visitor),
+            Marker::U64 => self.deserialize_u64(visitor),
+            Marker::FixNeg(_) | Marker::I8 => self.deserialize_i8(visitor),
+            Marker::I16 => self.deserialize_i16(visitor),
+            Marker::I32 => self.deserialize_i32(visitor),
+            Marker::I64 => self.deserialize_i64(visitor),
+            Marker::F32 => self.deserialize_f32(visitor),
+            Marker::F64 => self.deserialize_f64(visitor),
+            Marker::Null => {
+                self.eat_byte();
+                visitor.visit_unit()
+            }
+            Marker::True | Marker::False => self.deserialize_bool(visitor),
+            Marker::FixStr(_) | Marker::Str8 | Marker::Str16 | Marker::Str32 => self.deserialize_str(visitor),
+            Marker::Bin8 | Marker::Bin16 | Marker::Bin32 => self.deserialize_bytes(visitor),
+            Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => self.deserialize_seq(visitor),
+            Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => self.deserialize_map(visitor),
+            // extension types are presented as a sequence of the type and the data
+            #[cfg(feature = "ext")]
+            Marker::FixExt1
+            | Marker::FixExt2
+            | Marker::FixExt4
+            | Marker::FixExt8
+            | Marker::FixExt16
+            | Marker::Ext8
+            | Marker::Ext16
+            | Marker::Ext32 => {
+                let (header_len, data_len) = crate::ext::read_ext_len(&self.slice[self.index..])?;
+                let start = self.index + header_len;
+                let data = self.slice.get(start..start + data_len).ok_or(Error::EndOfBuffer)?;
+                let typ = self.slice[start - 1] as i8;
+                self.index = start + data_len;
+                visitor.visit_seq(ExtAccess::new(typ, data))
+            }
+            _ => Err(Error::InvalidType),
+        }
     }
 
     /// Used to throw out fields that we don’t want to keep in our structs.
     fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "ignored_any", &self);
-        self.deserialize_any(visitor)
+        self.expecting("ignored_any");
+        self.skip_any()?;
+        visitor.visit_unit()
     }
 
     /// Unsupported. Use a more specific deserialize_* method
     fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "unit", &self);
+        self.expecting("unit");
         let marker = self.peek().ok_or(Error::EndOfBuffer)?;
         match marker {
             Marker::Null | Marker::FixArray(0) => {
@@ -241,25 +475,28 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
     }
 
-    /// Unsupported. Use a more specific deserialize_* method
     fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "unit_struct", &self);
+        self.expecting("unit_struct");
         self.deserialize_unit(visitor)
     }
 
     fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "char", &self);
+        self.expecting("char");
         //TODO Need to decide how to encode this. Probably as a str?
         self.deserialize_str(visitor)
     }
 
     fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "newtype_struct", &self);
+        self.expecting("newtype_struct");
         visitor.visit_newtype_struct(self)
     }
 
     fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         print_debug::<V>("Deserializer::deserialize_", "string", &self);
+        self.expecting("string");
         self.deserialize_str(visitor)
     }
 }
@@ -297,12 +534,34 @@ impl fmt::Display for Error {
                 Error::CustomError => "Did not match deserializer's expected format.",
                 #[cfg(feature = "custom-error-messages")]
                 Error::CustomErrorWithMessage(msg) => msg.as_str(),
-                Error::NotAscii => "String contains non-ascii chars.",
+                Error::InvalidUtf8 => "String is not valid UTF-8.",
                 Error::InvalidNewTypeLength => "Invalid array length for newtype.",
                 Error::UnsupportedType => "The data type is not supported by this deserializer.",
+                Error::KeyNotFound => "Key not found in map.",
+                Error::DepthLimitExceeded => "Maximum nesting depth exceeded.",
+                Error::NeedMoreData => "More data needed to complete the token.",
+                Error::ReadFailed => "Read failed.",
+                Error::TrailingBytes => "Trailing bytes after the value.",
             }
         )
     }
     #[cfg(not(debug_assertions))]
     fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result { Ok(()) }
 }
+
+#[cfg(feature = "error-context")]
+impl fmt::Display for super::ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at offset {}", self.error, self.offset)?;
+        if let Some(marker) = self.marker {
+            write!(f, ", found 0x{:02x}", marker)?;
+        }
+        if let Some(expected) = self.expected {
+            write!(f, ", expected {}", expected)?;
+        }
+        if let Some(field) = self.field {
+            write!(f, ", in field `{}`", field)?;
+        }
+        write!(f, ")")
+    }
+}
@@ -5,10 +5,19 @@ use super::{print_debug, Deserializer, Error};
 pub(crate) struct SeqAccess<'a, 'b> {
     de: &'a mut Deserializer<'b>,
     count: usize,
+    index: usize,
 }
 
 impl<'a, 'b> SeqAccess<'a, 'b> {
-    pub fn new(de: &'a mut Deserializer<'b>, count: usize) -> Self { SeqAccess { de, count } }
+    pub fn new(de: &'a mut Deserializer<'b>, count: usize) -> Self { SeqAccess { de, count, index: 0 } }
+    /// Skips all elements that have not been consumed yet.
+    pub fn skip_remaining(&mut self) -> Result<(), Error> {
+        while self.count > 0 {
+            self.count -= 1;
+            self.de.skip_any()?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
@@ -19,9 +28,47 @@ impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
         print_debug::<T>("SeqAccess::", "next_element_seed", &self.de);
         if self.count > 0 {
             self.count -= 1;
+            self.de.record_field_index(self.index);
+            self.index += 1;
             Ok(Some(seed.deserialize(&mut *self.de)?))
         } else {
             Ok(None)
         }
     }
 }
+
+/// Presents an extension type as a sequence of its type and its data.
+#[cfg(feature = "ext")]
+pub(crate) struct ExtAccess<'a> {
+    typ: Option<i8>,
+    data: Option<&'a [u8]>,
+}
+
+#[cfg(feature = "ext")]
+impl<'a> ExtAccess<'a> {
+    pub const fn new(typ: i8, data: &'a [u8]) -> Self {
+        ExtAccess {
+            typ: Some(typ),
+            data: Some(data),
+        }
+    }
+}
+
+#[cfg(feature = "ext")]
+impl<'de> de::SeqAccess<'de> for ExtAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where T: de::DeserializeSeed<'de> {
+        use serde::de::{value::BorrowedBytesDeserializer, IntoDeserializer};
+        if let Some(typ) = self.typ.take() {
+            seed.deserialize(IntoDeserializer::<Error>::into_deserializer(typ)).map(Some)
+        } else if let Some(data) = self.data.take() {
+            seed.deserialize(BorrowedBytesDeserializer::<Error>::new(data)).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> { Some(usize::from(self.typ.is_some()) + usize::from(self.data.is_some())) }
+}
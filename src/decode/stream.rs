@@ -0,0 +1,130 @@
+use super::{read::Read, read_be_u16, read_be_u32, scalar_len, Error};
+use crate::{marker::Marker, value::decode_scalar, Value};
+
+/// A single token of a MessagePack stream as returned by [`StreamDecoder::next_token`].
+///
+/// Arrays and maps are announced by their length, the elements (keys and values in alternating order for maps)
+/// follow as separate tokens.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
+pub enum Token<'a> {
+    Nil,
+    Bool(bool),
+    /// Non-negative integer
+    UInt(u64),
+    /// Negative integer
+    Int(i64),
+    F32(f32),
+    F64(f64),
+    Str(&'a str),
+    Bin(&'a [u8]),
+    /// Start of an array with the given number of elements
+    ArrayStart(usize),
+    /// Start of a map with the given number of entries
+    MapStart(usize),
+    #[cfg(feature = "ext")]
+    Ext(i8, &'a [u8]),
+}
+
+/// Resumable decoder that pulls tokens from a [`Read`] source, e.g. a ring buffer that is filled by DMA.
+///
+/// Bytes are buffered until a whole token is available, so every token (including the content of strings,
+/// binaries and extensions) has to fit into the `N` bytes of the internal buffer. If the source runs dry in the
+/// middle of a token, [`Error::NeedMoreData`] is returned and the next call continues where this one left off.
+///
+/// ```
+/// # use embedded_msgpack::decode::{Error, StreamDecoder, Token};
+/// let mut decoder = StreamDecoder::<_, 16>::new(&[0x92, 0xa2, b'h'][..]);
+/// assert!(decoder.next_token()? == Token::ArrayStart(2));
+/// assert!(matches!(decoder.next_token(), Err(Error::NeedMoreData)));
+/// *decoder.get_mut() = &[b'i', 0x07];
+/// assert!(decoder.next_token()? == Token::Str("hi"));
+/// assert!(decoder.next_token()? == Token::UInt(7));
+/// # Ok::<(), Error>(())
+/// ```
+pub struct StreamDecoder<R, const N: usize> {
+    reader: R,
+    buf: [u8; N],
+    /// Number of valid bytes in `buf`
+    len: usize,
+    /// Number of bytes of the last returned token, which are dropped on the next call
+    consumed: usize,
+}
+
+impl<R: Read, const N: usize> StreamDecoder<R, N> {
+    pub const fn new(reader: R) -> Self {
+        StreamDecoder {
+            reader,
+            buf: [0; N],
+            len: 0,
+            consumed: 0,
+        }
+    }
+    pub const fn get_ref(&self) -> &R { &self.reader }
+    pub const fn get_mut(&mut self) -> &mut R { &mut self.reader }
+    /// Returns the source. Bytes that were already read from it but not returned as tokens are lost.
+    pub fn into_inner(self) -> R { self.reader }
+
+    /// Returns the next token.
+    ///
+    /// Fails with [`Error::NeedMoreData`] if the source has no more data before the token is complete and with
+    /// [`Error::OutOfBounds`] if the token does not fit into the internal buffer.
+    pub fn next_token(&mut self) -> Result<Token<'_>, Error> {
+        self.buf.copy_within(self.consumed..self.len, 0);
+        self.len -= self.consumed;
+        self.consumed = 0;
+        let n = loop {
+            match token_len(&self.buf[..self.len]) {
+                Err(Error::EndOfBuffer) if self.len == N => return Err(Error::OutOfBounds),
+                Err(Error::EndOfBuffer) => match self.reader.read(&mut self.buf[self.len..])? {
+                    0 => return Err(Error::NeedMoreData),
+                    read => self.len += read,
+                },
+                result => break result?,
+            }
+        };
+        let token = decode_token(&self.buf[..n])?;
+        self.consumed = n;
+        Ok(token)
+    }
+}
+
+/// Returns the encoded length of the token at the start of `buf`.
+pub(crate) fn token_len(buf: &[u8]) -> Result<usize, Error> {
+    let n = match Marker::from_u8(*buf.first().ok_or(Error::EndOfBuffer)?) {
+        Marker::FixArray(_) | Marker::FixMap(_) => 1,
+        Marker::Array16 | Marker::Map16 => 3,
+        Marker::Array32 | Marker::Map32 => 5,
+        _ => return scalar_len(buf),
+    };
+    if buf.len() < n {
+        return Err(Error::EndOfBuffer);
+    }
+    Ok(n)
+}
+
+/// Decodes a complete token.
+pub(crate) fn decode_token(buf: &[u8]) -> Result<Token<'_>, Error> {
+    Ok(match Marker::from_u8(buf[0]) {
+        Marker::FixArray(len) => Token::ArrayStart(len as usize),
+        Marker::FixMap(len) => Token::MapStart(len as usize),
+        Marker::Array16 if cfg!(feature = "array16") => Token::ArrayStart(read_be_u16(&buf[1..]) as usize),
+        Marker::Array32 if cfg!(feature = "array32") => Token::ArrayStart(read_be_u32(&buf[1..]) as usize),
+        Marker::Map16 if cfg!(feature = "map16") => Token::MapStart(read_be_u16(&buf[1..]) as usize),
+        Marker::Map32 if cfg!(feature = "map32") => Token::MapStart(read_be_u32(&buf[1..]) as usize),
+        Marker::Array16 | Marker::Array32 | Marker::Map16 | Marker::Map32 => return Err(Error::UnsupportedType),
+        _ => match decode_scalar(buf)?.0 {
+            Value::Nil => Token::Nil,
+            Value::Bool(v) => Token::Bool(v),
+            Value::UInt(v) => Token::UInt(v),
+            Value::Int(v) => Token::Int(v),
+            Value::F32(v) => Token::F32(v),
+            Value::F64(v) => Token::F64(v),
+            Value::Str(v) => Token::Str(v),
+            Value::Bin(v) => Token::Bin(v),
+            #[cfg(feature = "ext")]
+            Value::Ext(typ, data) => Token::Ext(typ, data),
+            Value::Array(_) | Value::Map(_) => unreachable!(),
+        },
+    })
+}
@@ -0,0 +1,159 @@
+use super::{read_array_len, read_map_len, skip_any, skip_any_with_max_depth, Error, DEFAULT_MAX_DEPTH};
+use crate::marker::Marker;
+use pastey::paste;
+
+/// Lazy, zero-copy view of a single encoded value.
+///
+/// Nothing is decoded until it is accessed, so looking up a few fields of a large message only has to skip over
+/// the other ones. Sub-views borrow from the same buffer and [`MessageView::as_slice`] can be passed to
+/// [`from_slice`](super::from_slice) to decode them completely.
+///
+/// ```
+/// # use embedded_msgpack::decode::MessageView;
+/// // {"hdr": {"dst": 513}}
+/// let buf = [0x81, 0xa3, b'h', b'd', b'r', 0x81, 0xa3, b'd', b's', b't', 0xcd, 0x02, 0x01];
+/// let view = MessageView::new(&buf)?;
+/// assert_eq!(view.get("hdr")?.get("dst")?.as_u16()?, 513);
+/// # Ok::<(), embedded_msgpack::decode::Error>(())
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
+pub struct MessageView<'a> {
+    buf: &'a [u8],
+}
+
+macro_rules! view_primitive {
+    ($ty:ident) => {
+        paste! {
+            pub fn [<as_ $ty>](&self) -> Result<$ty, Error> { Ok(super::[<read_ $ty>](self.buf)?.0) }
+        }
+    };
+}
+macro_rules! view_primitives {
+    ($($ty:ident),*) => { $( view_primitive!($ty); )* };
+}
+
+impl<'a> MessageView<'a> {
+    /// Creates a view of the first value in `buf`. Trailing bytes are ignored.
+    ///
+    /// Fails with [`Error::DepthLimitExceeded`] if arrays and maps in the value are nested deeper than
+    /// [`DEFAULT_MAX_DEPTH`].
+    pub fn new(buf: &'a [u8]) -> Result<Self, Error> { Self::with_max_depth::<DEFAULT_MAX_DEPTH>(buf) }
+
+    /// Like [`MessageView::new`], but with a nesting depth limit of `MAX_DEPTH` instead of [`DEFAULT_MAX_DEPTH`].
+    pub fn with_max_depth<const MAX_DEPTH: usize>(buf: &'a [u8]) -> Result<Self, Error> {
+        if buf.is_empty() {
+            return Err(Error::EndOfBuffer);
+        }
+        let (_, len) = skip_any_with_max_depth::<MAX_DEPTH>(buf)?;
+        Ok(MessageView { buf: &buf[..len] })
+    }
+
+    /// Returns the encoded bytes of the viewed value.
+    pub const fn as_slice(&self) -> &'a [u8] { self.buf }
+
+    fn marker(&self) -> Marker { Marker::from_u8(self.buf[0]) }
+
+    pub fn is_nil(&self) -> bool { self.marker() == Marker::Null }
+    pub fn is_array(&self) -> bool { matches!(self.marker(), Marker::FixArray(_) | Marker::Array16 | Marker::Array32) }
+    pub fn is_map(&self) -> bool { matches!(self.marker(), Marker::FixMap(_) | Marker::Map16 | Marker::Map32) }
+
+    /// Number of elements of an array or entries of a map.
+    pub fn len(&self) -> Result<usize, Error> {
+        if self.is_map() {
+            Ok(read_map_len(self.buf)?.0)
+        } else {
+            Ok(read_array_len(self.buf)?.0)
+        }
+    }
+    /// Returns `true` if this is an empty array or map.
+    pub fn is_empty(&self) -> Result<bool, Error> { Ok(self.len()? == 0) }
+
+    /// Looks up the value of the first entry with the string key `key` if this is a map.
+    ///
+    /// Fails with [`Error::KeyNotFound`] if there is no such entry. Entries with non-string keys are skipped.
+    pub fn get(&self, key: &str) -> Result<MessageView<'a>, Error> {
+        for entry in self.entries()? {
+            let (k, v) = entry?;
+            if k.as_str().ok() == Some(key) {
+                return Ok(v);
+            }
+        }
+        Err(Error::KeyNotFound)
+    }
+
+    /// Returns the element at `index` if this is an array.
+    ///
+    /// Fails with [`Error::OutOfBounds`] if the array is too short.
+    pub fn index(&self, index: usize) -> Result<MessageView<'a>, Error> { self.elements()?.nth(index).unwrap_or(Err(Error::OutOfBounds)) }
+
+    /// Returns an iterator over the elements if this is an array.
+    pub fn elements(&self) -> Result<Elements<'a>, Error> {
+        let (len, header_len) = read_array_len(self.buf)?;
+        Ok(Elements {
+            buf: &self.buf[header_len..],
+            remaining: len,
+        })
+    }
+    /// Returns an iterator over the keys and values if this is a map.
+    pub fn entries(&self) -> Result<Entries<'a>, Error> {
+        let (len, header_len) = read_map_len(self.buf)?;
+        Ok(Entries {
+            elements: Elements {
+                buf: &self.buf[header_len..],
+                remaining: len * 2,
+            },
+        })
+    }
+
+    view_primitives!(bool, u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+    pub fn as_str(&self) -> Result<&'a str, Error> { Ok(super::read_str(self.buf)?.0) }
+    pub fn as_bytes(&self) -> Result<&'a [u8], Error> { Ok(super::read_bin(self.buf)?.0) }
+}
+
+/// Iterator over the elements of an array. See [`MessageView::elements`].
+#[derive(Clone)]
+pub struct Elements<'a> {
+    buf: &'a [u8],
+    remaining: usize,
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Result<MessageView<'a>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // the enclosing view already checked the nesting depth
+        match skip_any(self.buf) {
+            Ok((_, len)) => {
+                let (view, rest) = self.buf.split_at(len);
+                self.buf = rest;
+                Some(Ok(MessageView { buf: view }))
+            }
+            Err(e) => {
+                self.remaining = 0;
+                Some(Err(e))
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) { (0, Some(self.remaining)) }
+}
+
+/// Iterator over the keys and values of a map. See [`MessageView::entries`].
+#[derive(Clone)]
+pub struct Entries<'a> {
+    elements: Elements<'a>,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<(MessageView<'a>, MessageView<'a>), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.elements.next()?;
+        let value = self.elements.next().unwrap_or(Err(Error::EndOfBuffer));
+        Some(key.and_then(|k| value.map(|v| (k, v))))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) { (0, Some(self.elements.remaining / 2)) }
+}
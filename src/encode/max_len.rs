@@ -0,0 +1,171 @@
+//! Worst-case encoded size of types, known at compile time.
+
+/// Types with an upper bound on their encoded size.
+///
+/// The bound holds for [`SerializeIntoSlice`](super::SerializeIntoSlice) and for the serde serializer with any
+/// [`Config`](super::serde::Config), so it can be used to size static buffers:
+///
+/// ```
+/// # use embedded_msgpack::encode::MaxEncodedLen;
+/// let mut buf = [0u8; <(u16, Option<bool>)>::MAX_ENCODED_LEN];
+/// # assert_eq!(buf.len(), 5);
+/// ```
+///
+/// With the `derive` feature it can be derived for structs and enums whose fields implement it. Serde attributes
+/// that change the layout, like `#[serde(tag = "...")]` or `#[serde(flatten)]`, are rejected by the derive.
+pub trait MaxEncodedLen {
+    /// Maximum number of bytes a value of this type encodes to.
+    const MAX_ENCODED_LEN: usize;
+}
+
+/// Length of the header of an array with `n` elements.
+pub const fn array_header_len(n: usize) -> usize {
+    if n <= 0x0f {
+        1
+    } else if n <= 0xffff {
+        3
+    } else {
+        5
+    }
+}
+/// Length of the header of a map with `n` entries.
+pub const fn map_header_len(n: usize) -> usize { array_header_len(n) }
+/// Length of the header of a string with `n` bytes.
+pub const fn str_header_len(n: usize) -> usize {
+    if n <= 0x1f {
+        1
+    } else if n <= 0xff {
+        2
+    } else if n <= 0xffff {
+        3
+    } else {
+        5
+    }
+}
+/// Encoded length of a string with `n` bytes.
+pub const fn str_len(n: usize) -> usize { str_header_len(n) + n }
+/// Encoded length of a binary with `n` bytes.
+pub const fn bin_len(n: usize) -> usize {
+    if n <= 0xff {
+        2 + n
+    } else if n <= 0xffff {
+        3 + n
+    } else {
+        5 + n
+    }
+}
+/// Encoded length of an extension with `n` data bytes, including the type.
+pub const fn ext_len(n: usize) -> usize {
+    match n {
+        #[cfg(feature = "fixext")]
+        1 | 2 | 4 | 8 | 16 => 2 + n,
+        _ if n <= 0xff => 3 + n,
+        _ if n <= 0xffff => 4 + n,
+        _ => n.saturating_add(6),
+    }
+}
+/// Encoded length of an unsigned integer, e.g. an enum variant index.
+pub const fn uint_len(n: u64) -> usize {
+    if n < 0x80 {
+        1
+    } else if n <= 0xff {
+        2
+    } else if n <= 0xffff {
+        3
+    } else if n <= 0xffff_ffff {
+        5
+    } else {
+        9
+    }
+}
+/// `const` version of [`core::cmp::max`].
+pub const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+macro_rules! max_encoded_len {
+    ($($ty:ty => $len:expr),* $(,)?) => {
+        $(
+            impl MaxEncodedLen for $ty {
+                const MAX_ENCODED_LEN: usize = $len;
+            }
+        )*
+    };
+}
+
+max_encoded_len!(
+    () => 1,
+    bool => 1,
+    u8 => 2,
+    u16 => 3,
+    u32 => 5,
+    u64 => 9,
+    i8 => 2,
+    i16 => 3,
+    i32 => 5,
+    i64 => 9,
+    f32 => 5,
+    f64 => 9,
+);
+
+impl<T: MaxEncodedLen + ?Sized> MaxEncodedLen for &T {
+    const MAX_ENCODED_LEN: usize = T::MAX_ENCODED_LEN;
+}
+
+impl<T: MaxEncodedLen> MaxEncodedLen for Option<T> {
+    const MAX_ENCODED_LEN: usize = max(1, T::MAX_ENCODED_LEN);
+}
+
+impl<T: MaxEncodedLen, const N: usize> MaxEncodedLen for [T; N] {
+    const MAX_ENCODED_LEN: usize = array_header_len(N) + N * T::MAX_ENCODED_LEN;
+}
+
+macro_rules! max_encoded_len_tuple {
+    ($len:expr; $($name:ident),+) => {
+        impl<$($name: MaxEncodedLen),+> MaxEncodedLen for ($($name,)+) {
+            const MAX_ENCODED_LEN: usize = array_header_len($len) $(+ $name::MAX_ENCODED_LEN)+;
+        }
+    };
+}
+
+max_encoded_len_tuple!(1; A);
+max_encoded_len_tuple!(2; A, B);
+max_encoded_len_tuple!(3; A, B, C);
+max_encoded_len_tuple!(4; A, B, C, D);
+max_encoded_len_tuple!(5; A, B, C, D, E);
+max_encoded_len_tuple!(6; A, B, C, D, E, F);
+
+#[cfg(feature = "heapless")]
+impl<const N: usize, L: heapless::LenType> MaxEncodedLen for heapless::String<N, L> {
+    const MAX_ENCODED_LEN: usize = str_len(N);
+}
+
+#[cfg(feature = "heapless")]
+impl<T: MaxEncodedLen, const N: usize, L: heapless::LenType> MaxEncodedLen for heapless::Vec<T, N, L> {
+    const MAX_ENCODED_LEN: usize = array_header_len(N) + N * T::MAX_ENCODED_LEN;
+}
+
+/// The largest extension the enabled features can encode, so this is only a useful bound without `ext16` and `ext32`.
+#[cfg(feature = "ext")]
+impl MaxEncodedLen for crate::Ext<'_> {
+    const MAX_ENCODED_LEN: usize = if cfg!(feature = "ext32") {
+        ext_len(0xffff_ffff)
+    } else if cfg!(feature = "ext16") {
+        ext_len(0xffff)
+    } else if cfg!(feature = "ext8") {
+        ext_len(0xff)
+    } else if cfg!(feature = "fixext") {
+        ext_len(16)
+    } else {
+        0
+    };
+}
+
+#[cfg(feature = "timestamp")]
+impl MaxEncodedLen for crate::timestamp::Timestamp {
+    const MAX_ENCODED_LEN: usize = if cfg!(feature = "timestamp96") { ext_len(12) } else { ext_len(8) };
+}
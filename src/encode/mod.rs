@@ -1,5 +1,14 @@
+pub mod max_len;
 #[cfg(feature = "serde")]
 pub mod serde;
+mod write;
+
+#[cfg(feature = "derive")]
+pub use embedded_msgpack_derive::{MaxEncodedLen, SerializeIntoSlice};
+pub use max_len::MaxEncodedLen;
+#[cfg(feature = "std")]
+pub use write::IoWriter;
+pub use write::{CountingWriter, SliceWriter, Write};
 
 use crate::marker::Marker;
 
@@ -15,9 +24,17 @@ pub enum Error {
     EndOfBuffer,
     /// Value was out of bounds.
     OutOfBounds,
-    /// Happens if the data type can not be serialized. For example if a sequence is not sized.
+    /// Happens if the data type can not be serialized. For example if a sequence is not sized and the writer
+    /// does not support back-patching its header.
     InvalidType,
     UnsupportedType,
+    /// The underlying writer failed.
+    WriteFailed,
+    /// A `Serialize` implementation reported an error.
+    CustomError,
+    /// A `Serialize` implementation reported an error with this message, truncated to fit.
+    #[cfg(feature = "custom-error-messages")]
+    CustomErrorWithMessage(heapless::String<64>),
 }
 
 impl ::core::fmt::Display for Error {
@@ -27,6 +44,10 @@ impl ::core::fmt::Display for Error {
             Error::InvalidType => f.write_str("Invalid type"),
             Error::EndOfBuffer => f.write_str("End of buffer"),
             Error::UnsupportedType => f.write_str("Unsupported type"),
+            Error::WriteFailed => f.write_str("Write failed"),
+            Error::CustomError => f.write_str("Custom error"),
+            #[cfg(feature = "custom-error-messages")]
+            Error::CustomErrorWithMessage(msg) => f.write_str(msg),
         }
     }
 }
@@ -99,201 +120,176 @@ pub(crate) fn write_be_f32(buf: &mut [u8], n: f32) { buf[..4].copy_from_slice(&n
 #[inline]
 pub(crate) fn write_be_f64(buf: &mut [u8], n: f64) { buf[..8].copy_from_slice(&n.to_be_bytes()); }
 
+/// Types that can be encoded as MessagePack without going through serde.
 pub trait SerializeIntoSlice {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error>;
+    /// Encodes `self` into `writer` and returns the number of bytes written.
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error>;
+    /// Encodes `self` into `buf` and returns the number of bytes written.
+    #[inline(always)]
+    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { self.write_into(buf) }
+    /// Returns the number of bytes [`SerializeIntoSlice::write_into`] would write, without writing anything.
+    #[inline(always)]
+    fn encoded_len(&self) -> Result<usize, Error> { self.write_into(CountingWriter::new()) }
 }
 
-pub fn serialize_u8(value: u8, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_u8<W: Write>(value: u8, mut writer: W) -> Result<usize, Error> {
     if value < 0x80 {
-        if buf.is_empty() {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = value;
+        writer.write_all(&[value])?;
         Ok(1)
     } else {
-        if buf.len() < 2 {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = Marker::U8.to_u8();
-        buf[1] = value;
+        writer.write_all(&[Marker::U8.to_u8(), value])?;
         Ok(2)
     }
 }
-pub fn serialize_u16(value: u16, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_u16<W: Write>(value: u16, mut writer: W) -> Result<usize, Error> {
     if let Ok(value) = u8::try_from(value) {
-        serialize_u8(value, buf)
+        serialize_u8(value, writer)
     } else {
-        if buf.len() < 3 {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = Marker::U16.to_u8();
+        let mut buf = [Marker::U16.to_u8(), 0, 0];
         write_be_u16(&mut buf[1..], value);
+        writer.write_all(&buf)?;
         Ok(3)
     }
 }
-pub fn serialize_u32(value: u32, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_u32<W: Write>(value: u32, mut writer: W) -> Result<usize, Error> {
     if let Ok(value) = u16::try_from(value) {
-        serialize_u16(value, buf)
+        serialize_u16(value, writer)
     } else {
-        if buf.len() < 5 {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = Marker::U32.to_u8();
+        let mut buf = [Marker::U32.to_u8(), 0, 0, 0, 0];
         write_be_u32(&mut buf[1..], value);
+        writer.write_all(&buf)?;
         Ok(5)
     }
 }
 #[cfg(feature = "u64")]
-pub fn serialize_u64(value: u64, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_u64<W: Write>(value: u64, mut writer: W) -> Result<usize, Error> {
     if let Ok(value) = u32::try_from(value) {
-        serialize_u32(value, buf)
+        serialize_u32(value, writer)
     } else {
-        if buf.len() < 9 {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = Marker::U64.to_u8();
+        let mut buf = [Marker::U64.to_u8(), 0, 0, 0, 0, 0, 0, 0, 0];
         write_be_u64(&mut buf[1..], value);
+        writer.write_all(&buf)?;
         Ok(9)
     }
 }
 #[allow(clippy::single_match_else)]
-pub fn serialize_i8(value: i8, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_i8<W: Write>(value: i8, mut writer: W) -> Result<usize, Error> {
     match value {
         -32..=0x7f => {
-            if buf.is_empty() {
-                return Err(Error::EndOfBuffer);
-            }
-            buf[0] = value as u8;
+            writer.write_all(&[value as u8])?;
             Ok(1)
         }
         _ => {
-            if buf.len() < 2 {
-                return Err(Error::EndOfBuffer);
-            }
-            buf[0] = Marker::I8.to_u8();
-            buf[1] = value as u8;
+            writer.write_all(&[Marker::I8.to_u8(), value as u8])?;
             Ok(2)
         }
     }
 }
-pub fn serialize_i16(value: i16, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_i16<W: Write>(value: i16, mut writer: W) -> Result<usize, Error> {
     // if value <= i8::max_value() as i16 && value >= i8::min_value() as i16 {
     if let Ok(value) = u16::try_from(value) {
-        serialize_u16(value, buf)
+        serialize_u16(value, writer)
     } else if let Ok(value) = i8::try_from(value) {
-        serialize_i8(value, buf)
+        serialize_i8(value, writer)
     } else {
-        if buf.len() < 3 {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = Marker::I16.to_u8();
+        let mut buf = [Marker::I16.to_u8(), 0, 0];
         write_be_i16(&mut buf[1..], value);
+        writer.write_all(&buf)?;
         Ok(3)
     }
 }
-pub fn serialize_i32(value: i32, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_i32<W: Write>(value: i32, mut writer: W) -> Result<usize, Error> {
     // if value <= i16::max_value() as i32 && value >= i16::min_value() as i32 {
     if let Ok(value) = u32::try_from(value) {
-        serialize_u32(value, buf)
+        serialize_u32(value, writer)
     } else if let Ok(value) = i16::try_from(value) {
-        serialize_i16(value, buf)
+        serialize_i16(value, writer)
     } else {
-        if buf.len() < 5 {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = Marker::I32.to_u8();
+        let mut buf = [Marker::I32.to_u8(), 0, 0, 0, 0];
         write_be_i32(&mut buf[1..], value);
+        writer.write_all(&buf)?;
         Ok(5)
     }
 }
 #[cfg(feature = "i64")]
-pub fn serialize_i64(value: i64, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_i64<W: Write>(value: i64, mut writer: W) -> Result<usize, Error> {
     #[cfg(feature = "u64")]
     if let Ok(value) = u64::try_from(value) {
-        return serialize_u64(value, buf);
+        return serialize_u64(value, writer);
     }
     if let Ok(value) = i32::try_from(value) {
-        serialize_i32(value, buf)
+        serialize_i32(value, writer)
     } else {
-        if buf.len() < 9 {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = Marker::I64.to_u8();
+        let mut buf = [Marker::I64.to_u8(), 0, 0, 0, 0, 0, 0, 0, 0];
         write_be_i64(&mut buf[1..], value);
+        writer.write_all(&buf)?;
         Ok(9)
     }
 }
 #[cfg(feature = "f32")]
-pub fn serialize_f32(value: f32, buf: &mut [u8]) -> Result<usize, Error> {
-    if buf.len() < 5 {
-        return Err(Error::EndOfBuffer);
-    }
-    buf[0] = Marker::F32.to_u8();
+pub fn serialize_f32<W: Write>(value: f32, mut writer: W) -> Result<usize, Error> {
+    let mut buf = [Marker::F32.to_u8(), 0, 0, 0, 0];
     write_be_f32(&mut buf[1..], value);
+    writer.write_all(&buf)?;
     Ok(5)
 }
 #[cfg(feature = "f64")]
-pub fn serialize_f64(value: f64, buf: &mut [u8]) -> Result<usize, Error> {
-    if buf.len() < 9 {
-        return Err(Error::EndOfBuffer);
-    }
-    buf[0] = Marker::F64.to_u8();
+pub fn serialize_f64<W: Write>(value: f64, mut writer: W) -> Result<usize, Error> {
+    let mut buf = [Marker::F64.to_u8(), 0, 0, 0, 0, 0, 0, 0, 0];
     write_be_f64(&mut buf[1..], value);
+    writer.write_all(&buf)?;
     Ok(9)
 }
 
 impl SerializeIntoSlice for u8 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_u8(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_u8(*self, writer) }
 }
 impl SerializeIntoSlice for u16 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_u16(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_u16(*self, writer) }
 }
 impl SerializeIntoSlice for u32 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_u32(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_u32(*self, writer) }
 }
 #[cfg(feature = "u64")]
 impl SerializeIntoSlice for u64 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_u64(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_u64(*self, writer) }
 }
 impl SerializeIntoSlice for i8 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_i8(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_i8(*self, writer) }
 }
 impl SerializeIntoSlice for i16 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_i16(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_i16(*self, writer) }
 }
 impl SerializeIntoSlice for i32 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_i32(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_i32(*self, writer) }
 }
 #[cfg(feature = "i64")]
 impl SerializeIntoSlice for i64 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_i64(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_i64(*self, writer) }
 }
 
 #[cfg(feature = "f32")]
 impl SerializeIntoSlice for f32 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_f32(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_f32(*self, writer) }
 }
 #[cfg(feature = "f64")]
 impl SerializeIntoSlice for f64 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_f64(*self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_f64(*self, writer) }
 }
 
 impl SerializeIntoSlice for bool {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> {
-        if buf.is_empty() {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = if *self { Marker::True.to_u8() } else { Marker::False.to_u8() };
+    fn write_into<W: Write>(&self, mut writer: W) -> Result<usize, Error> {
+        writer.write_all(&[if *self { Marker::True.to_u8() } else { Marker::False.to_u8() }])?;
         Ok(1)
     }
 }
@@ -301,21 +297,18 @@ impl SerializeIntoSlice for bool {
 impl<T> SerializeIntoSlice for Option<T>
 where T: SerializeIntoSlice
 {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> {
+    fn write_into<W: Write>(&self, mut writer: W) -> Result<usize, Error> {
         if let Some(value) = self {
-            SerializeIntoSlice::write_into_slice(value, buf)
+            SerializeIntoSlice::write_into(value, writer)
         } else {
-            if buf.is_empty() {
-                return Err(Error::EndOfBuffer);
-            }
-            buf[0] = Marker::Null.to_u8();
+            writer.write_all(&[Marker::Null.to_u8()])?;
             Ok(1)
         }
     }
 }
 impl SerializeIntoSlice for () {
     #[inline(always)]
-    fn write_into_slice(&self, _buf: &mut [u8]) -> Result<usize, Error> { Ok(0) }
+    fn write_into<W: Write>(&self, _writer: W) -> Result<usize, Error> { Ok(0) }
 }
 
 #[cfg(any(feature = "alloc", feature = "std"))]
@@ -397,35 +390,27 @@ impl<'a> ::serde::Deserialize<'a> for Binary<'a> {
 ///  - feature 'bin16' active: `n >= 2^16`
 ///  - else: `n >= 2^8`
 impl<'a> SerializeIntoSlice for Binary<'a> {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> {
+    fn write_into<W: Write>(&self, mut writer: W) -> Result<usize, Error> {
         let n = self.len();
         if let Ok(n8) = u8::try_from(n) {
-            if buf.len() < 2 + n {
-                return Err(Error::EndOfBuffer);
-            }
-            buf[0] = Marker::Bin8.to_u8();
-            buf[1] = n8;
-            buf[2..(2 + n)].clone_from_slice(self);
+            writer.write_all(&[Marker::Bin8.to_u8(), n8])?;
+            writer.write_all(self)?;
             return Ok(2 + n);
         }
         #[cfg(feature = "bin16")]
         if let Ok(n16) = u16::try_from(n) {
-            if buf.len() < 3 + n {
-                return Err(Error::EndOfBuffer);
-            }
-            buf[0] = Marker::Bin16.to_u8();
-            write_be_u16(&mut buf[1..], n16);
-            buf[3..(3 + n)].clone_from_slice(self);
+            let mut header = [Marker::Bin16.to_u8(), 0, 0];
+            write_be_u16(&mut header[1..], n16);
+            writer.write_all(&header)?;
+            writer.write_all(self)?;
             return Ok(3 + n);
         }
         #[cfg(feature = "bin32")]
         if let Ok(n32) = u32::try_from(n) {
-            if buf.len() < 5 + n {
-                return Err(Error::EndOfBuffer);
-            }
-            buf[0] = Marker::Bin32.to_u8();
-            write_be_u32(&mut buf[1..], n32);
-            buf[5..(5 + n)].clone_from_slice(self);
+            let mut header = [Marker::Bin32.to_u8(), 0, 0, 0, 0];
+            write_be_u32(&mut header[1..], n32);
+            writer.write_all(&header)?;
+            writer.write_all(self)?;
             return Ok(5 + n);
         }
         Err(Error::OutOfBounds)
@@ -438,8 +423,8 @@ where
     V: SerializeIntoSlice,
 {
     #[inline(always)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> {
-        let index = serialize_map_kay_value(&self.0, &self.1, buf)?;
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> {
+        let index = serialize_map_kay_value(&self.0, &self.1, writer)?;
         Ok(index)
     }
 }
@@ -451,55 +436,52 @@ where
 ///  - feature 'str16' active: `n >= 2^16`
 ///  - else: `n >= 2^8`
 impl SerializeIntoSlice for &str {
-    #[allow(clippy::cast_possible_truncation)]
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> {
-        let n = self.len();
-        match n {
-            // FIXSTR_SIZE
-            0..=0x1f => {
-                let header_len = 1;
-                if buf.len() < header_len + n {
-                    return Err(Error::EndOfBuffer);
-                }
-                buf[0] = Marker::FixStr(n as u8).to_u8();
-                buf[header_len..(header_len + n)].clone_from_slice(self.as_bytes());
-                Ok(header_len + n)
-            }
-            0x20..=0xff => {
-                let header_len = 2;
-                if buf.len() < header_len + n {
-                    return Err(Error::EndOfBuffer);
-                }
-                buf[0] = Marker::Str8.to_u8();
-                buf[1] = n as u8;
-                buf[header_len..(header_len + n)].clone_from_slice(self.as_bytes());
-                Ok(header_len + n)
+    fn write_into<W: Write>(&self, mut writer: W) -> Result<usize, Error> {
+        let header_len = serialize_str_start(self.len(), &mut writer)?;
+        writer.write_all(self.as_bytes())?;
+        Ok(header_len + self.len())
+    }
+}
+
+/// Size of the largest string header the enabled features allow.
+#[cfg(feature = "serde")]
+pub(crate) const MAX_STR_HEADER_LEN: usize = if cfg!(feature = "str32") {
+    5
+} else if cfg!(feature = "str16") {
+    3
+} else {
+    2
+};
+
+/// Writes the header of a string with `n` bytes.
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn serialize_str_start<W: Write>(n: usize, mut writer: W) -> Result<usize, Error> {
+    match n {
+        // FIXSTR_SIZE
+        0..=0x1f => {
+            writer.write_all(&[Marker::FixStr(n as u8).to_u8()])?;
+            Ok(1)
+        }
+        0x20..=0xff => {
+            writer.write_all(&[Marker::Str8.to_u8(), n as u8])?;
+            Ok(2)
+        }
+        _ => {
+            #[cfg(feature = "str16")]
+            if let Ok(n16) = u16::try_from(n) {
+                let mut header = [Marker::Str16.to_u8(), 0, 0];
+                write_be_u16(&mut header[1..], n16);
+                writer.write_all(&header)?;
+                return Ok(3);
             }
-            _ => {
-                #[cfg(feature = "str16")]
-                if let Ok(n16) = u16::try_from(n) {
-                    let header_len = 3;
-                    if buf.len() < header_len + n {
-                        return Err(Error::EndOfBuffer);
-                    }
-                    buf[0] = Marker::Str16.to_u8();
-                    write_be_u16(&mut buf[1..], n16);
-                    buf[header_len..(header_len + n)].clone_from_slice(self.as_bytes());
-                    return Ok(header_len + n);
-                }
-                #[cfg(feature = "str32")]
-                if let Ok(n32) = u32::try_from(n) {
-                    let header_len = 5;
-                    if buf.len() < header_len + n {
-                        return Err(Error::EndOfBuffer);
-                    }
-                    buf[0] = Marker::Str32.to_u8();
-                    write_be_u32(&mut buf[1..], n32);
-                    buf[header_len..(header_len + n)].clone_from_slice(self.as_bytes());
-                    return Ok(header_len + n);
-                }
-                Err(Error::OutOfBounds)
+            #[cfg(feature = "str32")]
+            if let Ok(n32) = u32::try_from(n) {
+                let mut header = [Marker::Str32.to_u8(), 0, 0, 0, 0];
+                write_be_u32(&mut header[1..], n32);
+                writer.write_all(&header)?;
+                return Ok(5);
             }
+            Err(Error::OutOfBounds)
         }
     }
 }
@@ -509,11 +491,11 @@ where
     K: SerializeIntoSlice,
     V: SerializeIntoSlice,
 {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> {
+    fn write_into<W: Write>(&self, mut writer: W) -> Result<usize, Error> {
         // serialize_sequence(self, SequenceType::Map, buf)
-        let mut index = serialize_map_start(self.len(), buf)?;
+        let mut index = serialize_map_start(self.len(), &mut writer)?;
         for kv in self.iter() {
-            index += kv.write_into_slice(&mut buf[index..])?;
+            index += kv.write_into(&mut writer)?;
         }
         Ok(index)
     }
@@ -522,11 +504,11 @@ where
 impl<T> SerializeIntoSlice for &[T]
 where T: SerializeIntoSlice
 {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> {
+    fn write_into<W: Write>(&self, mut writer: W) -> Result<usize, Error> {
         // serialize_sequence(self, SequenceType::Array, buf)
-        let mut index = serialize_array_start(self.len(), buf)?;
+        let mut index = serialize_array_start(self.len(), &mut writer)?;
         for i in self.iter() {
-            index += SerializeIntoSlice::write_into_slice(i, &mut buf[index..])?;
+            index += SerializeIntoSlice::write_into(i, &mut writer)?;
         }
         Ok(index)
     }
@@ -535,27 +517,37 @@ where T: SerializeIntoSlice
 impl<T, const N: usize> SerializeIntoSlice for [T; N]
 where T: SerializeIntoSlice
 {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { self.as_ref().write_into_slice(buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { self.as_ref().write_into(writer) }
 }
 
 #[cfg(feature = "heapless")]
 impl<T, const N: usize> SerializeIntoSlice for heapless::Vec<T, N>
 where T: SerializeIntoSlice
 {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { self.as_slice().write_into_slice(buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { self.as_slice().write_into(writer) }
 }
 
 #[cfg(feature = "alloc")]
 impl<T> SerializeIntoSlice for alloc::vec::Vec<T>
 where T: SerializeIntoSlice
 {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { self.as_slice().write_into_slice(buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { self.as_slice().write_into(writer) }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> SerializeIntoSlice for heapless::String<N> {
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { self.as_str().write_into(writer) }
+}
+
+#[cfg(feature = "alloc")]
+impl SerializeIntoSlice for alloc::string::String {
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { self.as_str().write_into(writer) }
 }
 
 impl<T> SerializeIntoSlice for &T
 where T: SerializeIntoSlice
 {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { (*self).write_into_slice(buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { (*self).write_into(writer) }
 }
 
 #[derive(Copy, Clone)]
@@ -564,18 +556,42 @@ pub enum SequenceType {
     Map,
 }
 impl SequenceType {
-    pub fn serialize_start(self, n: usize, buf: &mut [u8]) -> Result<usize, Error> {
+    pub fn serialize_start<W: Write>(self, n: usize, writer: W) -> Result<usize, Error> {
         match self {
-            SequenceType::Array => serialize_array_start(n, buf),
-            SequenceType::Map => serialize_map_start(n, buf),
+            SequenceType::Array => serialize_array_start(n, writer),
+            SequenceType::Map => serialize_map_start(n, writer),
+        }
+    }
+    /// Size of the largest header the enabled features allow for this sequence type.
+    #[cfg(feature = "serde")]
+    pub(crate) const fn max_header_len(self) -> usize {
+        match self {
+            SequenceType::Array => {
+                if cfg!(feature = "array32") {
+                    5
+                } else if cfg!(feature = "array16") {
+                    3
+                } else {
+                    1
+                }
+            }
+            SequenceType::Map => {
+                if cfg!(feature = "map32") {
+                    5
+                } else if cfg!(feature = "map16") {
+                    3
+                } else {
+                    1
+                }
+            }
         }
     }
 }
 
-pub fn serialize_sequence<T: SerializeIntoSlice>(seq: &[T], typ: SequenceType, buf: &mut [u8]) -> Result<usize, Error> {
-    let mut index = typ.serialize_start(seq.len(), buf)?;
+pub fn serialize_sequence<T: SerializeIntoSlice, W: Write>(seq: &[T], typ: SequenceType, mut writer: W) -> Result<usize, Error> {
+    let mut index = typ.serialize_start(seq.len(), &mut writer)?;
     for i in seq.iter() {
-        index += SerializeIntoSlice::write_into_slice(i, &mut buf[index..])?;
+        index += SerializeIntoSlice::write_into(i, &mut writer)?;
     }
     Ok(index)
 }
@@ -587,24 +603,23 @@ pub fn serialize_sequence<T: SerializeIntoSlice>(seq: &[T], typ: SequenceType, b
 ///  - feature 'array16' active: `n >= 2^16`
 ///  - else: `n >= 16`
 #[allow(clippy::cast_possible_truncation)]
-pub fn serialize_array_start(n: usize, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_array_start<W: Write>(n: usize, mut writer: W) -> Result<usize, Error> {
     if n <= crate::marker::FIXARRAY_SIZE as usize {
-        if buf.len() < 1 + n {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = Marker::FixArray(n as u8).to_u8();
+        writer.write_all(&[Marker::FixArray(n as u8).to_u8()])?;
         Ok(1)
     } else {
         #[cfg(feature = "array16")]
         if let Ok(n) = u16::try_from(n) {
-            buf[0] = Marker::Array16.to_u8();
-            write_be_u16(&mut buf[1..], n);
+            let mut header = [Marker::Array16.to_u8(), 0, 0];
+            write_be_u16(&mut header[1..], n);
+            writer.write_all(&header)?;
             return Ok(3);
         }
         #[cfg(feature = "array32")]
         if let Ok(n) = u32::try_from(n) {
-            buf[0] = Marker::Array32.to_u8();
-            write_be_u32(&mut buf[1..], n);
+            let mut header = [Marker::Array32.to_u8(), 0, 0, 0, 0];
+            write_be_u32(&mut header[1..], n);
+            writer.write_all(&header)?;
             return Ok(5);
         }
         Err(Error::OutOfBounds)
@@ -618,32 +633,35 @@ pub fn serialize_array_start(n: usize, buf: &mut [u8]) -> Result<usize, Error> {
 ///  - feature 'map16' active: `n >= 2^16`
 ///  - else: `n >= 16`
 #[allow(clippy::cast_possible_truncation)]
-pub fn serialize_map_start(n: usize, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_map_start<W: Write>(n: usize, mut writer: W) -> Result<usize, Error> {
     if n <= crate::marker::FIXMAP_SIZE as usize {
-        if buf.len() < 1 + n {
-            return Err(Error::EndOfBuffer);
-        }
-        buf[0] = Marker::FixMap(n as u8).to_u8();
+        writer.write_all(&[Marker::FixMap(n as u8).to_u8()])?;
         Ok(1)
     } else {
         #[cfg(feature = "map16")]
         if let Ok(n) = u16::try_from(n) {
-            buf[0] = Marker::Map16.to_u8();
-            write_be_u16(&mut buf[1..], n);
+            let mut header = [Marker::Map16.to_u8(), 0, 0];
+            write_be_u16(&mut header[1..], n);
+            writer.write_all(&header)?;
             return Ok(3);
         }
         #[cfg(feature = "map32")]
         if let Ok(n) = u32::try_from(n) {
-            buf[0] = Marker::Map32.to_u8();
-            write_be_u32(&mut buf[1..], n);
+            let mut header = [Marker::Map32.to_u8(), 0, 0, 0, 0];
+            write_be_u32(&mut header[1..], n);
+            writer.write_all(&header)?;
             return Ok(5);
         }
         Err(Error::OutOfBounds)
     }
 }
-pub fn serialize_map_kay_value<K: SerializeIntoSlice, V: SerializeIntoSlice>(key: &K, value: &V, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_map_kay_value<K: SerializeIntoSlice, V: SerializeIntoSlice, W: Write>(
+    key: &K,
+    value: &V,
+    mut writer: W,
+) -> Result<usize, Error> {
     let mut index = 0;
-    index += SerializeIntoSlice::write_into_slice(key, &mut buf[index..])?;
-    index += SerializeIntoSlice::write_into_slice(value, &mut buf[index..])?;
+    index += SerializeIntoSlice::write_into(key, &mut writer)?;
+    index += SerializeIntoSlice::write_into(value, &mut writer)?;
     Ok(index)
 }
@@ -1,23 +1,33 @@
-use super::{Error, Serializer};
+use super::{Error, Serializer, Write};
+use crate::encode::SequenceType;
 use serde::ser;
 
-pub struct SerializeMap<'a, 'b> {
-    ser: &'a mut Serializer<'b>,
+pub struct SerializeMap<'a, W> {
+    ser: &'a mut Serializer<W>,
+    /// Position of the first entry if the length was not known in advance
+    start: Option<usize>,
+    count: usize,
 }
 
-impl<'a, 'b> SerializeMap<'a, 'b> {
-    pub(crate) fn new(ser: &'a mut Serializer<'b>) -> Self { SerializeMap { ser } }
+impl<'a, W: Write> SerializeMap<'a, W> {
+    pub(crate) fn new(ser: &'a mut Serializer<W>, start: Option<usize>) -> Self { SerializeMap { ser, start, count: 0 } }
 }
 
-impl<'a, 'b> ser::SerializeMap for SerializeMap<'a, 'b> {
+impl<'a, W: Write> ser::SerializeMap for SerializeMap<'a, W> {
     type Ok = ();
     type Error = Error;
 
-    fn end(self) -> Result<Self::Ok, Self::Error> { Ok(()) }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self.start {
+            Some(start) => self.ser.end_sequence(SequenceType::Map, start, self.count),
+            None => Ok(()),
+        }
+    }
 
     fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<Self::Ok, Self::Error>
     where T: ser::Serialize {
         key.serialize(&mut *self.ser)?;
+        self.count += 1;
         Ok(())
     }
 
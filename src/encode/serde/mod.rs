@@ -1,15 +1,59 @@
+//! Serde serializer.
+//!
+//! Enum variants are encoded like `rmp-serde` does: unit variants as their name and all other variants as a
+//! map with a single entry from the variant name to the payload, i.e. `{"Variant": value}` for newtype variants,
+//! `{"Variant": [a, b]}` for tuple variants and `{"Variant": {"a": a, "b": b}}` for struct variants.
+//! With [`Config::with_variant_index`] the variant index is written in place of the name.
+//!
+//! Structs are encoded as maps from field names to values, or as arrays of the field values in declaration order
+//! with [`Config::with_struct_as_array`].
+
 use self::{map::SerializeMap, seq::SerializeSeq, struct_::SerializeStruct};
 use super::Error;
-use crate::encode::SerializeIntoSlice;
+use crate::encode::{CountingWriter, SequenceType, SerializeIntoSlice, SliceWriter, Write};
 
 mod map;
 mod seq;
 mod struct_;
 
-pub(crate) struct Serializer<'a> {
-    buf: &'a mut [u8],
+pub(crate) struct Serializer<W> {
+    writer: W,
     pos: usize,
     state: State,
+    config: Config,
+}
+
+/// Options controlling how the serializer encodes data.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
+pub struct Config {
+    variant_index: bool,
+    struct_as_array: bool,
+}
+
+impl Config {
+    pub const fn new() -> Self {
+        Config {
+            variant_index: false,
+            struct_as_array: false,
+        }
+    }
+    /// Encode enum variants by their index instead of their name.
+    ///
+    /// Unit variants then take a single byte for up to 128 variants. The deserializer accepts both forms.
+    pub const fn with_variant_index(mut self, variant_index: bool) -> Self {
+        self.variant_index = variant_index;
+        self
+    }
+    /// Encode structs (and struct variants) as arrays of their field values instead of maps with field names.
+    ///
+    /// This makes messages much smaller, but the field order becomes part of the format. Fields skipped with
+    /// `#[serde(skip_serializing_if = ...)]` shift all following fields and must not be used in this mode.
+    /// The deserializer accepts both forms.
+    pub const fn with_struct_as_array(mut self, struct_as_array: bool) -> Self {
+        self.struct_as_array = struct_as_array;
+        self
+    }
 }
 
 enum State {
@@ -20,31 +64,83 @@ enum State {
     Timestamp(Option<i64>, Option<u32>),
 }
 
-impl<'a> Serializer<'a> {
-    fn new(buf: &'a mut [u8]) -> Self {
+impl<W: Write> Serializer<W> {
+    const fn new(writer: W, config: Config) -> Self {
         Serializer {
-            buf,
+            writer,
             pos: 0,
             state: State::Normal,
+            config,
         }
     }
-    #[allow(clippy::clippy::needless_pass_by_value)]
+    #[allow(clippy::needless_pass_by_value)]
     fn append<S: SerializeIntoSlice>(&mut self, value: S) -> Result<(), Error> {
-        self.pos += value.write_into_slice(&mut self.buf[self.pos..])?;
+        self.pos += value.write_into(&mut self.writer)?;
+        Ok(())
+    }
+    /// Writes the header of an array or map.
+    ///
+    /// If `len` is unknown, the largest possible header is reserved instead and the position of the first
+    /// element is returned, which must later be passed to [`Serializer::end_sequence`].
+    fn start_sequence(&mut self, typ: SequenceType, len: Option<usize>) -> Result<Option<usize>, Error> {
+        if let Some(len) = len {
+            self.pos += typ.serialize_start(len, &mut self.writer)?;
+            Ok(None)
+        } else {
+            if !self.writer.can_splice() {
+                return Err(Error::InvalidType);
+            }
+            let reserved = typ.max_header_len();
+            self.writer.write_all(&[0; 5][..reserved])?;
+            self.pos += reserved;
+            Ok(Some(self.pos))
+        }
+    }
+    /// Writes the variant name or index, depending on the configuration.
+    fn append_variant(&mut self, variant_index: u32, variant: &'static str) -> Result<(), Error> {
+        if self.config.variant_index {
+            self.append(variant_index)
+        } else {
+            self.append(variant)
+        }
+    }
+    /// Writes the single-entry map header and the variant that precede the payload of a non-unit enum variant.
+    fn start_variant(&mut self, variant_index: u32, variant: &'static str) -> Result<(), Error> {
+        self.pos += super::serialize_map_start(1, &mut self.writer)?;
+        self.append_variant(variant_index, variant)
+    }
+    /// Writes the map or array header of a struct with `len` fields, depending on the configuration.
+    fn start_struct(&mut self, len: usize) -> Result<(), Error> {
+        let typ = if self.config.struct_as_array {
+            SequenceType::Array
+        } else {
+            SequenceType::Map
+        };
+        self.pos += typ.serialize_start(len, &mut self.writer)?;
+        Ok(())
+    }
+    /// Back-patches the header reserved by [`Serializer::start_sequence`] now that the number of elements is known.
+    /// The reserved header is shrunk to the actual header size by moving the elements.
+    fn end_sequence(&mut self, typ: SequenceType, start: usize, len: usize) -> Result<(), Error> {
+        let mut header = [0; 5];
+        let header_len = typ.serialize_start(len, &mut header[..])?;
+        let reserved = typ.max_header_len();
+        self.writer.splice(self.pos - start + reserved, reserved, &header[..header_len])?;
+        self.pos -= reserved - header_len;
         Ok(())
     }
 }
 
-impl<'a, 'b> ::serde::ser::Serializer for &'a mut Serializer<'b> {
+impl<'a, W: Write> ::serde::ser::Serializer for &'a mut Serializer<W> {
     type Ok = ();
     type Error = Error;
-    type SerializeSeq = SerializeSeq<'a, 'b>;
-    type SerializeTuple = SerializeSeq<'a, 'b>;
-    type SerializeTupleStruct = Unreachable;
-    type SerializeTupleVariant = &'a mut Serializer<'b>;
-    type SerializeMap = SerializeMap<'a, 'b>;
-    type SerializeStruct = SerializeStruct<'a, 'b>;
-    type SerializeStructVariant = Unreachable;
+    type SerializeSeq = SerializeSeq<'a, W>;
+    type SerializeTuple = SerializeSeq<'a, W>;
+    type SerializeTupleStruct = SerializeSeq<'a, W>;
+    type SerializeTupleVariant = SerializeSeq<'a, W>;
+    type SerializeMap = SerializeMap<'a, W>;
+    type SerializeStruct = SerializeStruct<'a, W>;
+    type SerializeStructVariant = SerializeStruct<'a, W>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> { self.append(v) }
     fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
@@ -96,7 +192,7 @@ impl<'a, 'b> ::serde::ser::Serializer for &'a mut Serializer<'b> {
                 let typ = typ.ok_or(Error::InvalidType)?;
                 self.state = State::Normal;
                 let ext = crate::Ext::new(typ, v);
-                self.pos += (&ext).write_into_slice(&mut self.buf[self.pos..])?;
+                self.pos += (&ext).write_into(&mut self.writer)?;
                 Ok(())
             }
             _ => {
@@ -116,12 +212,12 @@ impl<'a, 'b> ::serde::ser::Serializer for &'a mut Serializer<'b> {
         v.serialize(self)
     }
 
-    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> { unimplemented!() }
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> { self.serialize_none() }
 
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> { unimplemented!() }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> { self.serialize_none() }
 
-    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Self::Ok, Self::Error> {
-        self.serialize_str(variant)
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, variant: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.append_variant(variant_index, variant)
     }
 
     fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, v: &T) -> Result<Self::Ok, Self::Error>
@@ -132,40 +228,42 @@ impl<'a, 'b> ::serde::ser::Serializer for &'a mut Serializer<'b> {
     fn serialize_newtype_variant<T: ?Sized>(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ::serde::ser::Serialize,
     {
-        unimplemented!()
+        self.start_variant(variant_index, variant)?;
+        value.serialize(self)
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        self.pos += crate::encode::serialize_array_start(len.ok_or(Error::InvalidType)?, &mut self.buf[self.pos..])?;
-        Ok(SerializeSeq::new(self))
+        let start = self.start_sequence(SequenceType::Array, len)?;
+        Ok(SerializeSeq::new(self, start))
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> { self.serialize_seq(Some(len)) }
 
-    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        unimplemented!()
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        unimplemented!()
+        self.start_variant(variant_index, variant)?;
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        self.pos += super::serialize_map_start(len.ok_or(Error::InvalidType)?, &mut self.buf[self.pos..])?;
-        Ok(SerializeMap::new(self))
+        let start = self.start_sequence(SequenceType::Map, len)?;
+        Ok(SerializeMap::new(self, start))
     }
 
     fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, Self::Error> {
@@ -180,9 +278,7 @@ impl<'a, 'b> ::serde::ser::Serializer for &'a mut Serializer<'b> {
             crate::timestamp::TYPE_NAME => {
                 self.state = State::Timestamp(None, None);
             }
-            _ => {
-                self.pos += super::serialize_map_start(len, &mut self.buf[self.pos..])?;
-            }
+            _ => self.start_struct(len)?,
         }
         Ok(SerializeStruct::new(self))
     }
@@ -190,52 +286,195 @@ impl<'a, 'b> ::serde::ser::Serializer for &'a mut Serializer<'b> {
     fn serialize_struct_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        unimplemented!()
+        self.start_variant(variant_index, variant)?;
+        self.start_struct(len)?;
+        Ok(SerializeStruct::new(self))
     }
 
-    fn collect_str<T: ?Sized>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+    /// Formats `value` straight into the writer.
+    ///
+    /// If the writer supports [`Write::splice`] the largest string header is reserved and back-patched afterwards.
+    /// Otherwise `value` is formatted twice, once to determine its length and once to write it, so its `Display`
+    /// implementation must produce the same output both times.
+    fn collect_str<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where T: core::fmt::Display {
-        unimplemented!()
+        if self.writer.can_splice() {
+            let reserved = crate::encode::MAX_STR_HEADER_LEN;
+            self.writer.write_all(&[0; 5][..reserved])?;
+            let len = FmtWriter::format(&mut self.writer, value)?;
+            let mut header = [0; 5];
+            let header_len = crate::encode::serialize_str_start(len, &mut header[..])?;
+            self.writer.splice(len + reserved, reserved, &header[..header_len])?;
+            self.pos += header_len + len;
+        } else {
+            let len = FmtWriter::format(CountingWriter::new(), value)?;
+            self.pos += crate::encode::serialize_str_start(len, &mut self.writer)?;
+            if FmtWriter::format(&mut self.writer, value)? != len {
+                return Err(Error::InvalidType);
+            }
+            self.pos += len;
+        }
+        Ok(())
+    }
+}
+
+/// Adapter to format into a [`Write`] while counting the written bytes.
+struct FmtWriter<W> {
+    writer: W,
+    len: usize,
+    error: Option<Error>,
+}
+
+impl<W: Write> FmtWriter<W> {
+    /// Formats `value` into `writer` and returns the number of bytes written.
+    fn format<T: ?Sized + core::fmt::Display>(writer: W, value: &T) -> Result<usize, Error> {
+        use core::fmt::Write as _;
+        let mut w = FmtWriter {
+            writer,
+            len: 0,
+            error: None,
+        };
+        match write!(w, "{}", value) {
+            Ok(()) => Ok(w.len),
+            // the Display impl failed on its own
+            Err(_) => Err(w.error.unwrap_or(Error::CustomError)),
+        }
+    }
+}
+
+impl<W: Write> core::fmt::Write for FmtWriter<W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        match self.writer.write_all(s.as_bytes()) {
+            Ok(()) => {
+                self.len += s.len();
+                Ok(())
+            }
+            Err(e) => {
+                self.error = Some(e);
+                Err(core::fmt::Error)
+            }
+        }
     }
 }
 
-/// Serializes the given data structure as a JSON byte vector
+/// Serializes the given data structure into `buf` and returns the number of bytes written
 pub fn to_array<T>(value: &T, buf: &mut [u8]) -> Result<usize, Error>
 where T: ::serde::ser::Serialize + ?Sized {
-    let mut ser = Serializer::new(buf);
+    to_writer_with_config(value, SliceWriter::new(buf), Config::new())
+}
+
+/// Like [`to_array`], but with custom [`Config`]
+pub fn to_array_with_config<T>(value: &T, buf: &mut [u8], config: Config) -> Result<usize, Error>
+where T: ::serde::ser::Serialize + ?Sized {
+    to_writer_with_config(value, SliceWriter::new(buf), config)
+}
+
+/// Serializes the given data structure into `writer` and returns the number of bytes written
+pub fn to_writer<T, W>(value: &T, writer: W) -> Result<usize, Error>
+where
+    T: ::serde::ser::Serialize + ?Sized,
+    W: Write,
+{
+    to_writer_with_config(value, writer, Config::new())
+}
+
+/// Like [`to_writer`], but with custom [`Config`]
+pub fn to_writer_with_config<T, W>(value: &T, writer: W, config: Config) -> Result<usize, Error>
+where
+    T: ::serde::ser::Serialize + ?Sized,
+    W: Write,
+{
+    let mut ser = Serializer::new(writer, config);
     value.serialize(&mut ser)?;
     Ok(ser.pos)
 }
 
-impl ::serde::ser::Error for Error {
-    fn custom<T>(_msg: T) -> Self
-    where T: core::fmt::Display {
-        panic!()
-    }
+/// Returns the number of bytes [`to_array`] would write for `value`, without writing anything.
+///
+/// The buffer has to be larger than that if `value` contains sequences or maps of unknown length or strings written
+/// with `collect_str`. See [`required_buffer_len`].
+pub fn serialized_size<T>(value: &T) -> Result<usize, Error>
+where T: ::serde::ser::Serialize + ?Sized {
+    serialized_size_with_config(value, Config::new())
 }
 
-#[cfg(not(feature = "std"))]
-impl ::serde::ser::StdError for Error {}
+/// Like [`serialized_size`], but with custom [`Config`]
+pub fn serialized_size_with_config<T>(value: &T, config: Config) -> Result<usize, Error>
+where T: ::serde::ser::Serialize + ?Sized {
+    Ok(count(value, config)?.count())
+}
 
-impl<'a, 'b> ::serde::ser::SerializeTupleVariant for &'a mut Serializer<'b> {
-    type Ok = ();
-    type Error = Error;
+/// Returns the size of the buffer [`to_array`] needs for `value`, without writing anything.
+///
+/// Headers of sequences and maps of unknown length and of strings written with `collect_str` are reserved with the
+/// largest size first and shrunk afterwards, so this can be up to 4 bytes more than [`serialized_size`] for each of
+/// them that is open at the same time.
+pub fn required_buffer_len<T>(value: &T) -> Result<usize, Error>
+where T: ::serde::ser::Serialize + ?Sized {
+    required_buffer_len_with_config(value, Config::new())
+}
 
-    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
-    where T: ?Sized + serde::Serialize {
-        value.serialize(&mut **self)
+/// Like [`required_buffer_len`], but with custom [`Config`]
+pub fn required_buffer_len_with_config<T>(value: &T, config: Config) -> Result<usize, Error>
+where T: ::serde::ser::Serialize + ?Sized {
+    Ok(count(value, config)?.peak())
+}
+
+/// Serializes `value` into a [`CountingWriter`].
+fn count<T>(value: &T, config: Config) -> Result<CountingWriter, Error>
+where T: ::serde::ser::Serialize + ?Sized {
+    let mut writer = CountingWriter::new();
+    to_writer_with_config(value, &mut writer, config)?;
+    Ok(writer)
+}
+
+impl ::serde::ser::Error for Error {
+    #[cfg_attr(not(feature = "custom-error-messages"), allow(unused_variables))]
+    fn custom<T>(msg: T) -> Self
+    where T: core::fmt::Display {
+        #[cfg(not(feature = "custom-error-messages"))]
+        {
+            Error::CustomError
+        }
+        #[cfg(feature = "custom-error-messages")]
+        {
+            use core::fmt::Write;
+
+            let mut string = heapless::String::new();
+            // a message that does not fit is cut off and the error for that is ignored
+            let _ = write!(Truncating(&mut string), "{}", msg);
+            Error::CustomErrorWithMessage(string)
+        }
     }
+}
 
-    fn end(self) -> Result<(), Error> {
-        self.state = State::Normal;
-        Ok(())
+/// Adapter to format into a [`heapless::String`] that keeps as much of the message as fits.
+#[cfg(feature = "custom-error-messages")]
+struct Truncating<'a, const N: usize>(&'a mut heapless::String<N>);
+
+#[cfg(feature = "custom-error-messages")]
+impl<const N: usize> core::fmt::Write for Truncating<'_, N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut end = s.len().min(N - self.0.len());
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.0.push_str(&s[..end]).map_err(|_| core::fmt::Error)?;
+        if end == s.len() {
+            Ok(())
+        } else {
+            Err(core::fmt::Error)
+        }
     }
 }
 
+#[cfg(not(feature = "std"))]
+impl ::serde::ser::StdError for Error {}
+
 // impl<'a, 'b> ser::SerializeStruct for &'a mut Serializer<'b> {
 //     type Ok = ();
 //     type Error = Error;
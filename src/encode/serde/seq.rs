@@ -1,29 +1,39 @@
 use serde::ser;
 
-use super::{Error, Serializer};
+use super::{Error, Serializer, Write};
+use crate::encode::SequenceType;
 
-pub struct SerializeSeq<'a, 'b> {
-    ser: &'a mut Serializer<'b>,
+pub struct SerializeSeq<'a, W> {
+    ser: &'a mut Serializer<W>,
+    /// Position of the first element if the length was not known in advance
+    start: Option<usize>,
+    count: usize,
 }
 
-impl<'a, 'b> SerializeSeq<'a, 'b> {
-    pub(crate) fn new(ser: &'a mut Serializer<'b>) -> Self { SerializeSeq { ser } }
+impl<'a, W: Write> SerializeSeq<'a, W> {
+    pub(crate) fn new(ser: &'a mut Serializer<W>, start: Option<usize>) -> Self { SerializeSeq { ser, start, count: 0 } }
 }
 
-impl<'a, 'b> ser::SerializeSeq for SerializeSeq<'a, 'b> {
+impl<'a, W: Write> ser::SerializeSeq for SerializeSeq<'a, W> {
     type Ok = ();
     type Error = Error;
 
     fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<Self::Ok, Self::Error>
     where T: ser::Serialize {
         value.serialize(&mut *self.ser)?;
+        self.count += 1;
         Ok(())
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> { Ok(()) }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self.start {
+            Some(start) => self.ser.end_sequence(SequenceType::Array, start, self.count),
+            None => Ok(()),
+        }
+    }
 }
 
-impl<'a, 'b> ser::SerializeTuple for SerializeSeq<'a, 'b> {
+impl<'a, W: Write> ser::SerializeTuple for SerializeSeq<'a, W> {
     type Ok = ();
     type Error = Error;
 
@@ -34,3 +44,27 @@ impl<'a, 'b> ser::SerializeTuple for SerializeSeq<'a, 'b> {
 
     fn end(self) -> Result<Self::Ok, Self::Error> { ser::SerializeSeq::end(self) }
 }
+
+impl<'a, W: Write> ser::SerializeTupleStruct for SerializeSeq<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<Self::Ok, Self::Error>
+    where T: ser::Serialize {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> { ser::SerializeSeq::end(self) }
+}
+
+impl<'a, W: Write> ser::SerializeTupleVariant for SerializeSeq<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<Self::Ok, Self::Error>
+    where T: ser::Serialize {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> { ser::SerializeSeq::end(self) }
+}
@@ -1,14 +1,14 @@
-use super::{Error, Serializer, Unreachable};
+use super::{Error, Serializer, Unreachable, Write};
 use serde::ser::{self, Serialize};
 
-pub struct SerializeStruct<'a, 'b> {
-    ser: &'a mut Serializer<'b>,
+pub struct SerializeStruct<'a, W> {
+    ser: &'a mut Serializer<W>,
     #[cfg(feature = "timestamp")]
     ts_ser: TimestampSerializer,
 }
 
-impl<'a, 'b> SerializeStruct<'a, 'b> {
-    pub(crate) fn new(ser: &'a mut Serializer<'b>) -> Self {
+impl<'a, W: Write> SerializeStruct<'a, W> {
+    pub(crate) fn new(ser: &'a mut Serializer<W>) -> Self {
         SerializeStruct {
             ser,
             #[cfg(feature = "timestamp")]
@@ -20,7 +20,7 @@ impl<'a, 'b> SerializeStruct<'a, 'b> {
     }
 }
 
-impl<'a, 'b> ser::SerializeStruct for SerializeStruct<'a, 'b> {
+impl<'a, W: Write> ser::SerializeStruct for SerializeStruct<'a, W> {
     type Ok = ();
     type Error = Error;
 
@@ -28,7 +28,9 @@ impl<'a, 'b> ser::SerializeStruct for SerializeStruct<'a, 'b> {
     where T: ser::Serialize {
         match self.ser.state {
             super::State::Normal => {
-                key.serialize(&mut *self.ser)?;
+                if !self.ser.config.struct_as_array {
+                    key.serialize(&mut *self.ser)?;
+                }
                 value.serialize(&mut *self.ser)?;
             }
             #[cfg(feature = "ext")]
@@ -83,6 +85,18 @@ impl<'a, 'b> ser::SerializeStruct for SerializeStruct<'a, 'b> {
     }
 }
 
+impl<'a, W: Write> ser::SerializeStructVariant for SerializeStruct<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+    where T: ser::Serialize {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> { ser::SerializeStruct::end(self) }
+}
+
 #[cfg(feature = "timestamp")]
 struct TimestampSerializer {
     seconds: i64,
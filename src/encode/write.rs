@@ -0,0 +1,155 @@
+use super::Error;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+extern crate alloc;
+
+/// Byte sink the encoder writes into.
+///
+/// Implemented for `&mut [u8]` (the slice is advanced past the written bytes), [`SliceWriter`],
+/// `heapless::Vec<u8, N>`, `alloc::vec::Vec<u8>` and, via [`IoWriter`], any `std::io::Write`.
+pub trait Write {
+    /// Writes all of `data` or fails.
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error>;
+
+    /// Returns `true` if the writer supports [`Write::splice`].
+    fn can_splice(&self) -> bool { false }
+
+    /// Replaces the `reserved` bytes starting `offset` bytes before the current end with `data`
+    /// and moves the bytes following them to close the gap (`data.len() <= reserved <= offset`).
+    ///
+    /// This is used to back-patch the header of containers whose length is only known at the end,
+    /// so only writers that keep the written bytes addressable support it.
+    fn splice(&mut self, _offset: usize, _reserved: usize, _data: &[u8]) -> Result<(), Error> { Err(Error::InvalidType) }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    #[inline(always)]
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> { (**self).write_all(data) }
+    #[inline(always)]
+    fn can_splice(&self) -> bool { (**self).can_splice() }
+    #[inline(always)]
+    fn splice(&mut self, offset: usize, reserved: usize, data: &[u8]) -> Result<(), Error> { (**self).splice(offset, reserved, data) }
+}
+
+/// Splices `data` into the end of `written` as described by [`Write::splice`] and returns the new length.
+fn splice_slice(written: &mut [u8], offset: usize, reserved: usize, data: &[u8]) -> Result<usize, Error> {
+    if offset > written.len() || reserved > offset || data.len() > reserved {
+        return Err(Error::OutOfBounds);
+    }
+    let start = written.len() - offset;
+    written[start..start + data.len()].copy_from_slice(data);
+    written.copy_within(start + reserved.., start + data.len());
+    Ok(written.len() - (reserved - data.len()))
+}
+
+impl Write for &mut [u8] {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        if self.len() < data.len() {
+            return Err(Error::EndOfBuffer);
+        }
+        let (head, tail) = core::mem::take(self).split_at_mut(data.len());
+        head.copy_from_slice(data);
+        *self = tail;
+        Ok(())
+    }
+}
+
+/// Writer that only counts the bytes written to it. See [`SerializeIntoSlice::encoded_len`](super::SerializeIntoSlice::encoded_len).
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
+pub struct CountingWriter {
+    count: usize,
+    peak: usize,
+}
+
+impl CountingWriter {
+    pub const fn new() -> Self { CountingWriter { count: 0, peak: 0 } }
+    /// Number of bytes written so far.
+    pub const fn count(&self) -> usize { self.count }
+    /// Largest number of bytes there were at any time, including headers that were reserved and shrunk by
+    /// [`Write::splice`] afterwards. This is the buffer size needed to write the same data into a slice.
+    pub const fn peak(&self) -> usize { self.peak }
+}
+
+impl Write for CountingWriter {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.count += data.len();
+        self.peak = self.peak.max(self.count);
+        Ok(())
+    }
+    fn can_splice(&self) -> bool { true }
+    fn splice(&mut self, offset: usize, reserved: usize, data: &[u8]) -> Result<(), Error> {
+        if offset > self.count || reserved > offset || data.len() > reserved {
+            return Err(Error::OutOfBounds);
+        }
+        self.count -= reserved - data.len();
+        Ok(())
+    }
+}
+
+/// Writes into a fixed slice while keeping track of the number of bytes written.
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub const fn new(buf: &'a mut [u8]) -> Self { SliceWriter { buf, pos: 0 } }
+    /// Number of bytes written so far.
+    pub const fn position(&self) -> usize { self.pos }
+    /// Returns the written part of the underlying buffer.
+    pub fn into_written(self) -> &'a mut [u8] { &mut self.buf[..self.pos] }
+}
+
+impl<'a> Write for SliceWriter<'a> {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        let end = self.pos + data.len();
+        if self.buf.len() < end {
+            return Err(Error::EndOfBuffer);
+        }
+        self.buf[self.pos..end].copy_from_slice(data);
+        self.pos = end;
+        Ok(())
+    }
+    fn can_splice(&self) -> bool { true }
+    fn splice(&mut self, offset: usize, reserved: usize, data: &[u8]) -> Result<(), Error> {
+        self.pos = splice_slice(&mut self.buf[..self.pos], offset, reserved, data)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Write for heapless::Vec<u8, N> {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> { self.extend_from_slice(data).map_err(|_| Error::EndOfBuffer) }
+    fn can_splice(&self) -> bool { true }
+    fn splice(&mut self, offset: usize, reserved: usize, data: &[u8]) -> Result<(), Error> {
+        let len = splice_slice(self, offset, reserved, data)?;
+        self.truncate(len);
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl Write for alloc::vec::Vec<u8> {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+    fn can_splice(&self) -> bool { true }
+    fn splice(&mut self, offset: usize, reserved: usize, data: &[u8]) -> Result<(), Error> {
+        let len = splice_slice(self, offset, reserved, data)?;
+        self.truncate(len);
+        Ok(())
+    }
+}
+
+/// Adapter to encode into any [`std::io::Write`].
+///
+/// Every I/O error is reported as [`Error::WriteFailed`].
+#[cfg(feature = "std")]
+pub struct IoWriter<W>(pub W);
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Write for IoWriter<W> {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> { self.0.write_all(data).map_err(|_| Error::WriteFailed) }
+}
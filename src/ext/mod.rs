@@ -1,10 +1,13 @@
 #[cfg(feature = "timestamp")]
 pub mod timestamp;
 
-use crate::encode::{Binary, Error, SerializeIntoSlice};
+use crate::encode::{Binary, Error, SerializeIntoSlice, Write};
 #[allow(unused_imports)]
 use crate::marker::Marker;
-use core::{convert::TryInto, fmt::Display, marker::PhantomData};
+#[cfg(feature = "serde")]
+use core::marker::PhantomData;
+use core::{convert::TryInto, fmt::Display};
+#[cfg(feature = "serde")]
 use serde::{ser::SerializeStruct, Deserialize, Serialize};
 
 #[repr(transparent)]
@@ -12,12 +15,14 @@ use serde::{ser::SerializeStruct, Deserialize, Serialize};
 #[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
 struct ExtType(i8);
 
+#[cfg(feature = "serde")]
 impl Serialize for ExtType {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where S: serde::Serializer {
         Serialize::serialize(&self.0, serializer)
     }
 }
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for ExtType {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where D: serde::Deserializer<'de> {
@@ -134,29 +139,28 @@ pub(crate) fn read_ext_len<B: zerocopy::SplitByteSlice>(buf: B) -> Result<(usize
     }
 }
 
-pub fn serialize_ext<'a>(value: &Ext<'a>, buf: &mut [u8]) -> Result<usize, Error> {
+pub fn serialize_ext<'a, W: Write>(value: &Ext<'a>, mut writer: W) -> Result<usize, Error> {
     let typ = value.get_type();
     let data = value.get_data();
 
     let (marker, header_len) = get_ext_start(data.len())?;
-    if buf.len() < data.len() + header_len {
-        return Err(Error::EndOfBuffer);
-    }
-    buf[0] = marker.to_u8();
+    let mut header = [0; 6];
+    header[0] = marker.to_u8();
     if header_len > 2 {
         #[cfg(all(feature = "ext8", not(any(feature = "ext16", feature = "ext32"))))]
         {
-            buf[1] = data.len() as u8;
+            header[1] = data.len() as u8;
         }
         #[cfg(any(feature = "ext16", feature = "ext32"))]
         {
             use crate::encode::write_be_uint;
 
-            write_be_uint(&mut buf[1..], data.len() as u64, header_len - 2);
+            write_be_uint(&mut header[1..], data.len() as u64, header_len - 2);
         }
     }
-    buf[header_len - 1] = typ as u8;
-    buf[header_len..data.len() + header_len].clone_from_slice(&data);
+    header[header_len - 1] = typ as u8;
+    writer.write_all(&header[..header_len])?;
+    writer.write_all(data)?;
     Ok(data.len() + header_len)
 }
 
@@ -173,11 +177,14 @@ pub fn try_deserialize_ext<'a>(buf: &'a [u8]) -> Result<Ext<'a>, crate::decode::
 }
 
 impl<'a> SerializeIntoSlice for &Ext<'a> {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, Error> { serialize_ext(self, buf) }
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, Error> { serialize_ext(self, writer) }
 }
 
+#[cfg(feature = "serde")]
 pub(crate) const TYPE_NAME: &'static str = "$Ext";
+#[cfg(feature = "serde")]
 pub(crate) const FIELD_TYPE_NAME: &'static str = "type";
+#[cfg(feature = "serde")]
 pub(crate) const FIELD_DATA_NAME: &'static str = "data";
 
 #[cfg(feature = "serde")]
@@ -1,7 +1,7 @@
 use super::ExtType;
 use crate::{
-    decode::{read_be_u32, read_be_u64, Error as DeError},
-    encode::{write_be_u32, write_be_u64, Error as SerError, SerializeIntoSlice},
+    decode::{read_be_u32, read_be_u64, DeserializeFromSlice, Error as DeError},
+    encode::{write_be_u32, write_be_u64, Error as SerError, SerializeIntoSlice, Write},
     Ext,
 };
 use core::convert::{TryFrom, TryInto};
@@ -12,7 +12,7 @@ pub(crate) const TYPE_NAME: &'static str = "$Timestamp";
 pub(crate) const FIELD_SECONDS_NAME: &'static str = "seconds";
 pub(crate) const FIELD_NANOSECONDS_NAME: &'static str = "nanoseconds";
 
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Clone, Default, Ord, PartialOrd, Eq, PartialEq)]
 #[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
 pub struct Timestamp {
     seconds: i64,
@@ -74,15 +74,24 @@ impl Timestamp {
 }
 
 impl SerializeIntoSlice for Timestamp {
-    fn write_into_slice(&self, buf: &mut [u8]) -> Result<usize, SerError> {
+    fn write_into<W: Write>(&self, writer: W) -> Result<usize, SerError> {
         let mut tmp = [0; 12];
         let ext = self.to_ext(&mut tmp)?;
-        crate::ext::serialize_ext(&ext, buf)
+        crate::ext::serialize_ext(&ext, writer)
     }
 }
 
 pub fn try_deserialize(buf: &[u8]) -> Result<Timestamp, DeError> { crate::ext::try_deserialize_ext(&buf)?.try_into() }
 
+impl DeserializeFromSlice for Timestamp {
+    fn from_slice(&mut self, buf: &[u8]) -> Result<usize, DeError> {
+        let ext = crate::ext::try_deserialize_ext(buf)?;
+        let (header_len, data_len) = crate::ext::read_ext_len(buf)?;
+        *self = ext.try_into()?;
+        Ok(header_len + data_len)
+    }
+}
+
 impl<'a> TryFrom<Ext<'a>> for Timestamp {
     type Error = DeError;
 
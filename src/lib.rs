@@ -4,11 +4,14 @@
 #![warn(clippy::missing_const_for_fn)]
 #![warn(clippy::missing_panics_doc)]
 
+#[cfg(feature = "embedded-io-async")]
+pub mod asynch;
 pub mod decode;
 pub mod encode;
 #[cfg(feature = "ext")]
 pub mod ext;
 mod marker;
+pub mod value;
 
 #[cfg(feature = "timestamp")]
 pub use ext::timestamp;
@@ -16,3 +19,4 @@ pub use ext::timestamp;
 pub use ext::Ext;
 #[cfg(feature = "serde_bytes")]
 pub use serde_bytes::Bytes;
+pub use value::Value;
@@ -0,0 +1,402 @@
+//! Dynamically typed MessagePack values for inspecting messages without a matching Rust type.
+//!
+//! All strings and binaries borrow from the decoded buffer. Without allocations the elements of arrays and maps are
+//! stored in a caller-provided node arena (see [`Value::decode_in`]), with `alloc` they can also be collected into
+//! vectors (see [`Value::decode`]).
+
+use crate::{
+    decode::{self, Error, DEFAULT_MAX_DEPTH},
+    encode::{self, Binary, SerializeIntoSlice, Write},
+    marker::Marker,
+};
+use core::{convert::TryFrom, ops::Deref};
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+extern crate alloc;
+#[cfg(any(feature = "alloc", feature = "std"))]
+use alloc::vec::Vec;
+
+/// Elements of an array or keys and values of a map.
+///
+/// Borrows the elements from a node arena or, with `alloc`, owns them.
+#[derive(Clone)]
+#[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
+pub struct Nodes<'a>(
+    #[cfg(not(any(feature = "alloc", feature = "std")))] &'a [Value<'a>],
+    #[cfg(any(feature = "alloc", feature = "std"))] Storage<'a>,
+);
+
+// Not a `Cow`, because that would make `Value` invariant over its lifetime.
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[derive(Clone)]
+#[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
+enum Storage<'a> {
+    Borrowed(&'a [Value<'a>]),
+    Owned(Vec<Value<'a>>),
+}
+
+impl<'a> Nodes<'a> {
+    #[cfg(not(any(feature = "alloc", feature = "std")))]
+    #[inline]
+    pub const fn new(nodes: &'a [Value<'a>]) -> Self { Nodes(nodes) }
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    #[inline]
+    pub const fn new(nodes: &'a [Value<'a>]) -> Self { Nodes(Storage::Borrowed(nodes)) }
+}
+
+impl<'a> Deref for Nodes<'a> {
+    type Target = [Value<'a>];
+    #[cfg(not(any(feature = "alloc", feature = "std")))]
+    #[inline]
+    fn deref(&self) -> &Self::Target { self.0 }
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        match &self.0 {
+            Storage::Borrowed(nodes) => nodes,
+            Storage::Owned(nodes) => nodes,
+        }
+    }
+}
+impl<'a> PartialEq for Nodes<'a> {
+    fn eq(&self, other: &Self) -> bool { **self == **other }
+}
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl<'a> From<Vec<Value<'a>>> for Nodes<'a> {
+    #[inline]
+    fn from(nodes: Vec<Value<'a>>) -> Self { Nodes(Storage::Owned(nodes)) }
+}
+
+/// A single MessagePack value borrowing from the buffer it was decoded from.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(any(test, feature = "derive-debug"), derive(core::fmt::Debug))]
+pub enum Value<'a> {
+    Nil,
+    Bool(bool),
+    /// Non-negative integer
+    UInt(u64),
+    /// Negative integer
+    Int(i64),
+    F32(f32),
+    F64(f64),
+    Str(&'a str),
+    Bin(&'a [u8]),
+    Array(Nodes<'a>),
+    /// Keys and values of the map in alternating order (`[k0, v0, k1, v1, ...]`).
+    Map(Nodes<'a>),
+    #[cfg(feature = "ext")]
+    Ext(i8, &'a [u8]),
+}
+
+impl<'a> Value<'a> {
+    /// Creates an array from borrowed elements.
+    pub const fn array(elements: &'a [Value<'a>]) -> Self { Value::Array(Nodes::new(elements)) }
+    /// Creates a map from borrowed keys and values in alternating order.
+    ///
+    /// Encoding the map fails if `entries` has an odd length.
+    pub const fn map(entries: &'a [Value<'a>]) -> Self { Value::Map(Nodes::new(entries)) }
+
+    /// Decodes the first value in `buf` and returns it together with the number of bytes read.
+    ///
+    /// The elements of all arrays and maps are stored in `arena`, which therefore needs one slot for every
+    /// array element and two for every map entry. If it is too small [`Error::OutOfBounds`] is returned.
+    pub fn decode_in(buf: &'a [u8], arena: &'a mut [Value<'a>]) -> Result<(Self, usize), Error> {
+        Self::decode_in_with_max_depth(buf, arena, DEFAULT_MAX_DEPTH)
+    }
+    /// Like [`Value::decode_in`], but with nesting limited to `max_depth` instead of [`DEFAULT_MAX_DEPTH`].
+    pub fn decode_in_with_max_depth(buf: &'a [u8], arena: &'a mut [Value<'a>], max_depth: usize) -> Result<(Self, usize), Error> {
+        let mut arena = arena;
+        decode_in(buf, &mut arena, max_depth)
+    }
+
+    /// Decodes the first value in `buf` and returns it together with the number of bytes read.
+    ///
+    /// The elements of arrays and maps are collected into vectors.
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    pub fn decode(buf: &'a [u8]) -> Result<(Self, usize), Error> { Self::decode_with_max_depth(buf, DEFAULT_MAX_DEPTH) }
+    /// Like [`Value::decode`], but with nesting limited to `max_depth` instead of [`DEFAULT_MAX_DEPTH`].
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    pub fn decode_with_max_depth(buf: &'a [u8], max_depth: usize) -> Result<(Self, usize), Error> {
+        let marker = Marker::from_u8(*buf.first().ok_or(Error::EndOfBuffer)?);
+        let (len, mut index) = match marker {
+            Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => decode::read_array_len(buf)?,
+            Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+                let (len, header_len) = decode::read_map_len(buf)?;
+                (len.checked_mul(2).ok_or(Error::OutOfBounds)?, header_len)
+            }
+            _ => return decode_scalar(buf),
+        };
+        let max_depth = max_depth.checked_sub(1).ok_or(Error::DepthLimitExceeded)?;
+        // every element needs at least one byte, so this does not trust the length blindly
+        let mut nodes = Vec::with_capacity(len.min(buf.len() - index));
+        for _ in 0..len {
+            let (value, n) = Self::decode_with_max_depth(&buf[index..], max_depth)?;
+            nodes.push(value);
+            index += n;
+        }
+        let nodes = Nodes::from(nodes);
+        match marker {
+            Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => Ok((Value::Array(nodes), index)),
+            _ => Ok((Value::Map(nodes), index)),
+        }
+    }
+
+    pub const fn is_nil(&self) -> bool { matches!(self, Value::Nil) }
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::UInt(v) => Some(*v),
+            Value::Int(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::UInt(v) => i64::try_from(*v).ok(),
+            Value::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::F32(v) => Some(f64::from(*v)),
+            Value::F64(v) => Some(*v),
+            _ => None,
+        }
+    }
+    pub const fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub const fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Value::Bin(b) => Some(b),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&[Value<'a>]> {
+        match self {
+            Value::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+    /// Returns an iterator over the key/value pairs if this is a map.
+    pub fn entries(&self) -> Option<impl Iterator<Item = (&Value<'a>, &Value<'a>)>> {
+        match self {
+            Value::Map(entries) => Some(entries.chunks_exact(2).map(|e| (&e[0], &e[1]))),
+            _ => None,
+        }
+    }
+    /// Looks up the value of the first entry with the string key `key` if this is a map.
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> { self.entries()?.find(|(k, _)| k.as_str() == Some(key)).map(|(_, v)| v) }
+}
+
+/// Decodes everything except arrays and maps.
+pub(crate) fn decode_scalar(buf: &[u8]) -> Result<(Value<'_>, usize), Error> {
+    let marker = Marker::from_u8(*buf.first().ok_or(Error::EndOfBuffer)?);
+    Ok(match marker {
+        Marker::Null => (Value::Nil, 1),
+        Marker::True | Marker::False => (Value::Bool(marker == Marker::True), 1),
+        Marker::FixPos(_) | Marker::U8 | Marker::U16 | Marker::U32 | Marker::U64 => {
+            let (v, n) = decode::read_u64(buf)?;
+            (Value::UInt(v), n)
+        }
+        Marker::FixNeg(_) | Marker::I8 | Marker::I16 | Marker::I32 | Marker::I64 => {
+            let (v, n) = decode::read_i64(buf)?;
+            // values that fit are always represented as `UInt`, regardless of the marker
+            (u64::try_from(v).map_or(Value::Int(v), Value::UInt), n)
+        }
+        Marker::F32 => {
+            let (v, n) = decode::read_f32(buf)?;
+            (Value::F32(v), n)
+        }
+        Marker::F64 => {
+            let (v, n) = decode::read_f64(buf)?;
+            (Value::F64(v), n)
+        }
+        Marker::FixStr(_) | Marker::Str8 | Marker::Str16 | Marker::Str32 => {
+            let (s, n) = decode::read_str(buf)?;
+            (Value::Str(s), n)
+        }
+        Marker::Bin8 | Marker::Bin16 | Marker::Bin32 => {
+            let (b, n) = decode::read_bin(buf)?;
+            (Value::Bin(b), n)
+        }
+        #[cfg(feature = "ext")]
+        Marker::FixExt1
+        | Marker::FixExt2
+        | Marker::FixExt4
+        | Marker::FixExt8
+        | Marker::FixExt16
+        | Marker::Ext8
+        | Marker::Ext16
+        | Marker::Ext32 => {
+            let (header_len, data_len) = crate::ext::read_ext_len(buf)?;
+            let data = buf.get(header_len..header_len + data_len).ok_or(Error::EndOfBuffer)?;
+            (Value::Ext(buf[header_len - 1] as i8, data), header_len + data_len)
+        }
+        _ => return Err(Error::InvalidType),
+    })
+}
+
+fn decode_in<'a>(buf: &'a [u8], arena: &mut &'a mut [Value<'a>], max_depth: usize) -> Result<(Value<'a>, usize), Error> {
+    let marker = Marker::from_u8(*buf.first().ok_or(Error::EndOfBuffer)?);
+    let (len, mut index) = match marker {
+        Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => decode::read_array_len(buf)?,
+        Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+            let (len, header_len) = decode::read_map_len(buf)?;
+            (len.checked_mul(2).ok_or(Error::OutOfBounds)?, header_len)
+        }
+        _ => return decode_scalar(buf),
+    };
+    let max_depth = max_depth.checked_sub(1).ok_or(Error::DepthLimitExceeded)?;
+    if arena.len() < len {
+        return Err(Error::OutOfBounds);
+    }
+    let (nodes, rest) = core::mem::take(arena).split_at_mut(len);
+    *arena = rest;
+    for node in nodes.iter_mut() {
+        let (value, n) = decode_in(&buf[index..], arena, max_depth)?;
+        *node = value;
+        index += n;
+    }
+    let nodes: &'a [Value<'a>] = nodes;
+    match marker {
+        Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => Ok((Value::array(nodes), index)),
+        _ => Ok((Value::map(nodes), index)),
+    }
+}
+
+impl<'a> SerializeIntoSlice for Value<'a> {
+    fn write_into<W: Write>(&self, mut writer: W) -> Result<usize, encode::Error> { write_value(self, &mut writer) }
+}
+
+// Recurses with the same writer type, `write_into` would instantiate itself for `&mut W`, `&mut &mut W`, ...
+fn write_value<W: Write + ?Sized>(value: &Value<'_>, writer: &mut W) -> Result<usize, encode::Error> {
+    match value {
+        Value::Nil => Option::<u8>::None.write_into(writer),
+        Value::Bool(b) => b.write_into(writer),
+        Value::UInt(v) => match u32::try_from(*v) {
+            Ok(v) => v.write_into(writer),
+            #[cfg(feature = "u64")]
+            Err(_) => v.write_into(writer),
+            #[cfg(not(feature = "u64"))]
+            Err(_) => Err(encode::Error::OutOfBounds),
+        },
+        Value::Int(v) => match i32::try_from(*v) {
+            Ok(v) => v.write_into(writer),
+            #[cfg(feature = "i64")]
+            Err(_) => v.write_into(writer),
+            #[cfg(not(feature = "i64"))]
+            Err(_) => Err(encode::Error::OutOfBounds),
+        },
+        #[cfg(feature = "f32")]
+        Value::F32(v) => v.write_into(writer),
+        #[cfg(feature = "f64")]
+        Value::F64(v) => v.write_into(writer),
+        #[cfg(not(feature = "f32"))]
+        Value::F32(_) => Err(encode::Error::UnsupportedType),
+        #[cfg(not(feature = "f64"))]
+        Value::F64(_) => Err(encode::Error::UnsupportedType),
+        Value::Str(s) => s.write_into(writer),
+        Value::Bin(b) => Binary::new(b).write_into(writer),
+        Value::Array(elements) => {
+            let mut n = encode::serialize_array_start(elements.len(), &mut *writer)?;
+            for e in elements.iter() {
+                n += write_value(e, writer)?;
+            }
+            Ok(n)
+        }
+        Value::Map(entries) => {
+            if entries.len() % 2 != 0 {
+                return Err(encode::Error::InvalidType);
+            }
+            let mut n = encode::serialize_map_start(entries.len() / 2, &mut *writer)?;
+            for e in entries.iter() {
+                n += write_value(e, writer)?;
+            }
+            Ok(n)
+        }
+        #[cfg(feature = "ext")]
+        Value::Ext(typ, data) => crate::ext::serialize_ext(&crate::Ext::new(*typ, data), writer),
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> ::serde::Serialize for Value<'a> {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ::serde::ser::SerializeMap;
+        match self {
+            Value::Nil => serializer.serialize_none(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::UInt(v) => serializer.serialize_u64(*v),
+            Value::Int(v) => serializer.serialize_i64(*v),
+            Value::F32(v) => serializer.serialize_f32(*v),
+            Value::F64(v) => serializer.serialize_f64(*v),
+            Value::Str(s) => serializer.serialize_str(s),
+            Value::Bin(b) => serializer.serialize_bytes(b),
+            Value::Array(elements) => serializer.collect_seq(elements.iter()),
+            Value::Map(entries) => {
+                if entries.len() % 2 != 0 {
+                    return Err(::serde::ser::Error::custom("map with a key without value"));
+                }
+                let mut map = serializer.serialize_map(Some(entries.len() / 2))?;
+                for e in entries.chunks_exact(2) {
+                    map.serialize_entry(&e[0], &e[1])?;
+                }
+                map.end()
+            }
+            #[cfg(feature = "ext")]
+            Value::Ext(typ, data) => crate::Ext::new(*typ, data).serialize(serializer),
+        }
+    }
+}
+
+#[cfg(all(feature = "serde", any(feature = "alloc", feature = "std")))]
+struct ValueVisitor;
+
+#[cfg(all(feature = "serde", any(feature = "alloc", feature = "std")))]
+impl<'de> ::serde::de::Visitor<'de> for ValueVisitor {
+    type Value = Value<'de>;
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result { formatter.write_str("any borrowed MessagePack value") }
+    fn visit_unit<E: ::serde::de::Error>(self) -> Result<Self::Value, E> { Ok(Value::Nil) }
+    fn visit_none<E: ::serde::de::Error>(self) -> Result<Self::Value, E> { Ok(Value::Nil) }
+    fn visit_some<D: ::serde::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        ::serde::Deserialize::deserialize(deserializer)
+    }
+    fn visit_bool<E: ::serde::de::Error>(self, v: bool) -> Result<Self::Value, E> { Ok(Value::Bool(v)) }
+    fn visit_u64<E: ::serde::de::Error>(self, v: u64) -> Result<Self::Value, E> { Ok(Value::UInt(v)) }
+    fn visit_i64<E: ::serde::de::Error>(self, v: i64) -> Result<Self::Value, E> { Ok(u64::try_from(v).map_or(Value::Int(v), Value::UInt)) }
+    fn visit_f32<E: ::serde::de::Error>(self, v: f32) -> Result<Self::Value, E> { Ok(Value::F32(v)) }
+    fn visit_f64<E: ::serde::de::Error>(self, v: f64) -> Result<Self::Value, E> { Ok(Value::F64(v)) }
+    fn visit_borrowed_str<E: ::serde::de::Error>(self, v: &'de str) -> Result<Self::Value, E> { Ok(Value::Str(v)) }
+    fn visit_borrowed_bytes<E: ::serde::de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> { Ok(Value::Bin(v)) }
+    fn visit_seq<A: ::serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(e) = seq.next_element()? {
+            elements.push(e);
+        }
+        Ok(Value::Array(elements.into()))
+    }
+    fn visit_map<A: ::serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0) * 2);
+        while let Some((k, v)) = map.next_entry()? {
+            entries.push(k);
+            entries.push(v);
+        }
+        Ok(Value::Map(entries.into()))
+    }
+}
+
+/// Extension types are seen as arrays of their type and data through serde. Use [`Value::decode`] to keep them.
+#[cfg(all(feature = "serde", any(feature = "alloc", feature = "std")))]
+impl<'de> ::serde::Deserialize<'de> for Value<'de> {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> { deserializer.deserialize_any(ValueVisitor) }
+}
@@ -0,0 +1,93 @@
+use core::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+use embedded_msgpack::{
+    asynch::{read_message, write_message, Error},
+    decode,
+};
+use serde::{Deserialize, Serialize};
+
+/// Polls a future that never waits, which is the case for all readers and writers used here.
+fn block_on<F: Future>(future: F) -> F::Output {
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future is pending"),
+    }
+}
+
+/// Reader handing out one byte per call.
+struct Trickle<'a>(&'a [u8]);
+
+impl embedded_io_async::ErrorType for Trickle<'_> {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_io_async::Read for Trickle<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() || self.0.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Reading<'a> {
+    sensor: &'a str,
+    values: [i16; 3],
+}
+
+#[test]
+fn asynch_roundtrip() {
+    let mut stream = [0u8; 64];
+    let mut scratch = [0u8; 32];
+    let mut writer = &mut stream[..];
+    let first = Reading {
+        sensor: "temp",
+        values: [-1, 300, 7],
+    };
+    let n1 = block_on(write_message(&mut writer, &first, &mut scratch)).unwrap();
+    let n2 = block_on(write_message(&mut writer, &[1u8, 2], &mut scratch)).unwrap();
+    let len = n1 + n2;
+
+    // reading byte by byte must not consume anything after the first message
+    let mut buf = [0u8; 32];
+    let mut reader = Trickle(&stream[..len]);
+    let reading: Reading = block_on(read_message(&mut reader, &mut buf)).unwrap();
+    assert_eq!(reading, first);
+    assert_eq!(reader.0, &stream[n1..len]);
+    let mut buf = [0u8; 32];
+    let rest: [u8; 2] = block_on(read_message(&mut reader, &mut buf)).unwrap();
+    assert_eq!(rest, [1, 2]);
+
+    let mut reader = &stream[..len];
+    let mut buf = [0u8; 32];
+    let reading: Reading = block_on(read_message(&mut reader, &mut buf)).unwrap();
+    assert_eq!(reading, first);
+    assert_eq!(reader.len(), n2);
+}
+#[test]
+fn asynch_errors() {
+    let mut buf = [0u8; 4];
+    let mut reader = &[0xa5, b'h', b'e', b'l', b'l', b'o'][..];
+    let result = block_on(read_message::<_, &str>(&mut reader, &mut buf));
+    assert!(matches!(result, Err(Error::Decode(decode::Error::OutOfBounds))));
+
+    let mut buf = [0u8; 8];
+    let mut reader = &[0x92, 0x01][..];
+    let result = block_on(read_message::<_, [u8; 2]>(&mut reader, &mut buf));
+    assert!(matches!(result, Err(Error::Decode(decode::Error::EndOfBuffer))));
+
+    let mut reader = &[0x91, 0xc1][..];
+    let result = block_on(read_message::<_, [u8; 1]>(&mut reader, &mut buf));
+    assert!(matches!(result, Err(Error::Decode(decode::Error::InvalidType))));
+
+    let mut scratch = [0u8; 2];
+    let mut stream = [0u8; 8];
+    let result = block_on(write_message(&mut &mut stream[..], "hello", &mut scratch));
+    assert!(matches!(result, Err(Error::Encode(_))));
+}
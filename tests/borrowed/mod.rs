@@ -0,0 +1,71 @@
+use embedded_msgpack::decode::{Decode, Error, MessageView};
+
+fn decode<'a, T: Decode<'a>>(buf: &'a [u8]) -> T {
+    let (value, len) = T::decode(buf).unwrap();
+    assert_eq!(len, buf.len());
+    value
+}
+
+#[test]
+fn decode_primitives() {
+    assert!(decode::<bool>(&[0xc3]));
+    assert_eq!(decode::<u16>(&[0xcd, 0x01, 0x2c]), 300);
+    assert_eq!(decode::<i8>(&[0xff]), -1);
+    assert_eq!(decode::<Option<u8>>(&[0xc0]), None);
+    assert_eq!(decode::<Option<u8>>(&[0x05]), Some(5));
+    assert_eq!(decode::<[i16; 2]>(&[0x92, 0x01, 0xd0, 0x80]), [1, -128]);
+    assert!(matches!(<[u8; 2]>::decode(&[0x91, 0x01]), Err(Error::OutOfBounds)));
+    assert!(matches!(u8::decode(&[]), Err(Error::EndOfBuffer)));
+}
+#[test]
+fn decode_borrowed() {
+    let buf = [0xa5, b'h', b'e', b'l', b'l', b'o'];
+    let s: &str = decode(&buf);
+    assert_eq!(s, "hello");
+    assert_eq!(s.as_ptr(), buf[1..].as_ptr());
+
+    let buf = [0xc4, 0x02, 0xde, 0xad];
+    assert_eq!(decode::<&[u8]>(&buf), &[0xde, 0xad]);
+    assert_eq!(decode::<[&str; 2]>(&[0x92, 0xa1, b'a', 0xa0]), ["a", ""]);
+    assert_eq!(decode::<Option<&str>>(&[0xc0]), None);
+    assert!(matches!(<&str>::decode(&[0xa3, b'a']), Err(Error::EndOfBuffer)));
+
+    let view: MessageView = decode(&[0x92, 0x01, 0xa1, b'x']);
+    assert_eq!(view.index(1).unwrap().as_str().unwrap(), "x");
+}
+#[test]
+fn decode_sequence() {
+    // "id", 7, [true] written back to back
+    let buf = [0xa2, b'i', b'd', 0x07, 0x91, 0xc3];
+    let (name, n) = <&str>::decode(&buf).unwrap();
+    let (value, m) = u32::decode(&buf[n..]).unwrap();
+    let (flags, _) = <[bool; 1]>::decode(&buf[n + m..]).unwrap();
+    assert_eq!((name, value, flags), ("id", 7, [true]));
+}
+#[cfg(feature = "heapless")]
+#[test]
+fn decode_heapless() {
+    assert_eq!(decode::<heapless::String<8>>(&[0xa2, b'h', b'i']), "hi");
+    assert_eq!(decode::<heapless::Vec<&str, 2>>(&[0x92, 0xa1, b'a', 0xa1, b'b']), ["a", "b"]);
+    assert!(matches!(
+        heapless::Vec::<u8, 2>::decode(&[0x93, 0x01, 0x02, 0x03]),
+        Err(Error::OutOfBounds)
+    ));
+}
+#[cfg(feature = "alloc")]
+#[test]
+fn decode_alloc() {
+    use embedded_msgpack::Value;
+    assert_eq!(decode::<String>(&[0xa2, b'h', b'i']), "hi");
+    assert_eq!(decode::<Vec<Option<&str>>>(&[0x92, 0xc0, 0xa1, b'a']), [None, Some("a")]);
+    assert_eq!(decode::<Value>(&[0x91, 0xa1, b'a']), Value::Array(vec![Value::Str("a")].into()));
+}
+#[cfg(feature = "timestamp")]
+#[test]
+fn decode_ext() {
+    use embedded_msgpack::{timestamp::Timestamp, Ext};
+    let ext: Ext = decode(&[0xd4, 0x05, 0xaa]);
+    assert_eq!(ext, Ext::new(5, &[0xaa]));
+    let ts: Timestamp = decode(&[0xd6, 0xff, 0x00, 0x00, 0x00, 0x01]);
+    assert_eq!(ts, Timestamp::new(1, 0).unwrap());
+}
@@ -78,6 +78,33 @@ fn decode_bin32() {
     );
 }
 
+#[test]
+fn decode_str() {
+    test_decode("abc", &[&[0xa3, b'a', b'b', b'c'], &[0xd9, 0x03, b'a', b'b', b'c']]);
+    test_decode("20 °C", &[&[0xa6, b'2', b'0', b' ', 0xc2, 0xb0, b'C']]);
+    test_decode("Größe", &[&[0xa7, b'G', b'r', 0xc3, 0xb6, 0xc3, 0x9f, b'e']]);
+    assert!(matches!(
+        embedded_msgpack::decode::from_slice::<&str>(&[0xa2, 0xc3, 0x28]),
+        Err(embedded_msgpack::decode::Error::InvalidUtf8)
+    ));
+}
+#[test]
+fn decode_str_utf8_mode() {
+    use embedded_msgpack::decode::{from_slice_with_config, Config, Utf8Mode};
+    let data: &[u8] = &[0xa4, b'a', 0xff, b'b', b'c'];
+    let raw = Config::new().with_utf8_mode(Utf8Mode::Raw);
+    let v: &[u8] = from_slice_with_config(data, raw).unwrap();
+    assert_eq!(v, &[b'a', 0xff, b'b', b'c']);
+    // valid strings are still passed as strings
+    let v: &str = from_slice_with_config(&[0xa1, b'a'], raw).unwrap();
+    assert_eq!(v, "a");
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    {
+        let lossy = Config::new().with_utf8_mode(Utf8Mode::Lossy);
+        let v: String = from_slice_with_config(data, lossy).unwrap();
+        assert_eq!(v, "a\u{fffd}bc");
+    }
+}
 #[test]
 fn decode_enum() {
     use serde::Deserialize;
@@ -208,3 +235,241 @@ fn decode_timestamp() {
         ]],
     );
 }
+
+#[test]
+fn decode_enum_map() {
+    use serde::Deserialize;
+    #[derive(Deserialize, PartialEq, Eq, Debug)]
+    enum Test {
+        Unit,
+        NewType(i32),
+        Tuple(i32, u8),
+        Struct { a: i32 },
+    }
+    test_decode(
+        Test::Unit,
+        &[&[0xa4, 0x55, 0x6e, 0x69, 0x74], &[0x81, 0xa4, 0x55, 0x6e, 0x69, 0x74, 0xc0]],
+    );
+    test_decode(Test::NewType(-1), &[&[0x81, 0xa7, 0x4e, 0x65, 0x77, 0x54, 0x79, 0x70, 0x65, 0xff]]);
+    test_decode(Test::Tuple(1, 2), &[&[0x81, 0xa5, 0x54, 0x75, 0x70, 0x6c, 0x65, 0x92, 0x01, 0x02]]);
+    test_decode(
+        Test::Struct { a: 3 },
+        &[&[0x81, 0xa6, 0x53, 0x74, 0x72, 0x75, 0x63, 0x74, 0x81, 0xa1, 0x61, 0x03]],
+    );
+}
+#[test]
+fn decode_enum_index() {
+    use serde::Deserialize;
+    #[derive(Deserialize, PartialEq, Eq, Debug)]
+    enum Test {
+        A,
+        B(u8),
+    }
+    test_decode(Test::A, &[&[0x00], &[0xa1, 0x41]]);
+    test_decode(Test::B(7), &[&[0x81, 0x01, 0x07], &[0x81, 0xa1, 0x42, 0x07]]);
+}
+#[test]
+fn decode_any() {
+    use serde::Deserialize;
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(untagged)]
+    enum Untagged<'a> {
+        Int(i64),
+        Float(f64),
+        Str(&'a str),
+        List([u8; 2]),
+        Point { x: i8, y: i8 },
+    }
+    test_decode(Untagged::Int(-300), &[&[0xd1, 0xfe, 0xd4]]);
+    test_decode(Untagged::Int(5), &[&[0x05], &[0xcd, 0x00, 0x05]]);
+    test_decode(Untagged::Float(0.5), &[&[0xcb, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0]]);
+    test_decode(Untagged::Str("ab"), &[&[0xa2, b'a', b'b']]);
+    test_decode(Untagged::List([1, 2]), &[&[0x92, 0x01, 0x02]]);
+    test_decode(Untagged::Point { x: 1, y: -1 }, &[&[0x82, 0xa1, b'x', 0x01, 0xa1, b'y', 0xff]]);
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(tag = "type")]
+    enum Tagged {
+        Move { dx: u8 },
+        Stop,
+    }
+    test_decode(
+        Tagged::Move { dx: 3 },
+        &[&[
+            0x82, 0xa4, b't', b'y', b'p', b'e', 0xa4, b'M', b'o', b'v', b'e', 0xa2, b'd', b'x', 0x03,
+        ]],
+    );
+    test_decode(Tagged::Stop, &[&[0x81, 0xa4, b't', b'y', b'p', b'e', 0xa4, b'S', b't', b'o', b'p']]);
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Inner {
+        b: bool,
+    }
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Outer {
+        a: u8,
+        #[serde(flatten)]
+        inner: Inner,
+    }
+    test_decode(
+        Outer {
+            a: 1,
+            inner: Inner { b: true },
+        },
+        &[
+            &[0x82, 0xa1, b'a', 0x01, 0xa1, b'b', 0xc3],
+            &[0x82, 0xa1, b'b', 0xc3, 0xa1, b'a', 0x01],
+        ],
+    );
+
+    let v: serde_json::Value =
+        embedded_msgpack::decode::from_slice(&[0x83, 0xa1, b'a', 0xc0, 0xa1, b'b', 0x92, 0xff, 0xc2, 0xa1, b'c', 0xcc, 0xc8]).unwrap();
+    assert_eq!(v, serde_json::json!({"a": null, "b": [-1, false], "c": 200}));
+}
+#[cfg(feature = "ext")]
+#[test]
+fn decode_any_ext() {
+    use serde::Deserialize;
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(untagged)]
+    enum Field<'a> {
+        Num(u8),
+        Ext(i8, &'a [u8]),
+    }
+    test_decode(
+        Field::Ext(5, &[1, 2]),
+        &[&[0xd5, 0x05, 0x01, 0x02], &[0xc7, 0x02, 0x05, 0x01, 0x02]],
+    );
+    test_decode(Field::Num(9), &[&[0x09]]);
+}
+#[test]
+fn decode_depth_limit() {
+    use embedded_msgpack::decode::{
+        from_slice, from_slice_with_config, skip_any, skip_any_with_max_depth, Config, Error, DEFAULT_MAX_DEPTH,
+    };
+    // 1 KiB of nested single element arrays
+    let mut nested = [0x91u8; 1024];
+    nested[1023] = 0xc0;
+    assert!(matches!(from_slice::<serde_json::Value>(&nested), Err(Error::DepthLimitExceeded)));
+    // ignored values are skipped without recursion
+    assert!(from_slice::<serde::de::IgnoredAny>(&nested).is_ok());
+    assert_eq!(skip_any(&nested[..]).unwrap().1, 1024);
+    assert!(matches!(
+        skip_any_with_max_depth::<DEFAULT_MAX_DEPTH>(&nested),
+        Err(Error::DepthLimitExceeded)
+    ));
+
+    let data = &nested[1024 - DEFAULT_MAX_DEPTH - 1..];
+    assert_eq!(skip_any_with_max_depth::<DEFAULT_MAX_DEPTH>(data).unwrap().1, DEFAULT_MAX_DEPTH + 1);
+    assert!(from_slice::<serde_json::Value>(data).is_ok());
+    let data = &nested[1024 - 4..];
+    assert_eq!(skip_any_with_max_depth::<3>(data).unwrap().1, 4);
+    assert!(matches!(skip_any_with_max_depth::<2>(data), Err(Error::DepthLimitExceeded)));
+    let v: [[[Option<u8>; 1]; 1]; 1] = from_slice_with_config(data, Config::new().with_max_depth(3)).unwrap();
+    assert_eq!(v, [[[None]]]);
+    assert!(matches!(
+        from_slice_with_config::<[[[Option<u8>; 1]; 1]; 1]>(data, Config::new().with_max_depth(2)),
+        Err(Error::DepthLimitExceeded)
+    ));
+}
+#[cfg(feature = "error-context")]
+#[test]
+fn decode_error_context() {
+    use embedded_msgpack::decode::{from_slice_with_context, Config, Error};
+    use serde::Deserialize;
+    #[derive(Deserialize, Debug)]
+    struct Header {
+        src: u8,
+        dst: u16,
+    }
+    #[derive(Deserialize, Debug)]
+    struct Message {
+        hdr: Header,
+        len: u8,
+    }
+
+    // {"hdr": {"src": 1, "dst": true}, "len": 2}
+    let buf = [
+        0x82, 0xa3, b'h', b'd', b'r', 0x82, 0xa3, b's', b'r', b'c', 0x01, 0xa3, b'd', b's', b't', 0xc3, 0xa3, b'l', b'e', b'n', 0x02,
+    ];
+    let err = from_slice_with_context::<Message>(&buf, Config::new()).unwrap_err();
+    assert!(matches!(err.error(), Error::InvalidType));
+    assert_eq!(err.offset(), 15);
+    assert_eq!(err.marker(), Some(0xc3));
+    assert_eq!(err.expected(), Some("u16"));
+    assert_eq!(err.field(), Some("dst"));
+
+    // [[1, 2], 300]
+    let buf = [0x92, 0x92, 0x01, 0x02, 0xcd, 0x01, 0x2c];
+    let err = from_slice_with_context::<Message>(&buf, Config::new()).unwrap_err();
+    assert_eq!(err.offset(), 4);
+    assert_eq!(err.expected(), Some("u8"));
+    assert_eq!(err.field(), Some("len"));
+
+    let msg: Message = from_slice_with_context(&[0x92, 0x92, 0x01, 0x02, 0x03], Config::new()).unwrap();
+    assert_eq!((msg.hdr.src, msg.hdr.dst, msg.len), (1, 2, 3));
+
+    // [[1, <truncated u16>
+    let buf = [0x92, 0x92, 0x01, 0xcd, 0x01];
+    let err = from_slice_with_context::<Message>(&buf, Config::new()).unwrap_err();
+    assert_eq!(err.offset(), 3);
+    assert_eq!(err.marker(), Some(0xcd));
+    assert_eq!(err.field(), Some("dst"));
+    assert!(matches!(err.into_error(), Error::EndOfBuffer));
+}
+#[test]
+fn decode_iter_from_slice() {
+    use embedded_msgpack::decode::{iter_from_slice, Error};
+    use serde::Deserialize;
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Entry<'a> {
+        level: u8,
+        text: &'a str,
+    }
+
+    // {"level": 1, "text": "up"}, [2, "down", "extra"], {"level": 3, "text": "x"}
+    let buf = [
+        0x82, 0xa5, b'l', b'e', b'v', b'e', b'l', 0x01, 0xa4, b't', b'e', b'x', b't', 0xa2, b'u', b'p', 0x93, 0x02, 0xa4, b'd', b'o', b'w',
+        b'n', 0xa5, b'e', b'x', b't', b'r', b'a', 0x82, 0xa5, b'l', b'e', b'v', b'e', b'l', 0x03, 0xa4, b't', b'e', b'x', b't', 0xa1, b'x',
+    ];
+    let entries: Vec<Entry> = iter_from_slice(&buf).collect::<Result<_, _>>().unwrap();
+    assert_eq!(
+        entries,
+        [
+            Entry { level: 1, text: "up" },
+            Entry { level: 2, text: "down" },
+            Entry { level: 3, text: "x" },
+        ]
+    );
+
+    // tuples skip the elements they do not read
+    let pairs: Vec<(u8, u8)> = iter_from_slice(&[0x93, 0x01, 0x02, 0x03, 0x92, 0x04, 0x05])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(pairs, [(1, 2), (4, 5)]);
+
+    let mut iter = iter_from_slice::<u8>(&[0x01, 0xcd, 0x01]);
+    assert_eq!(iter.next().unwrap().unwrap(), 1);
+    assert_eq!(iter.remainder(), &[0xcd, 0x01]);
+    assert!(matches!(iter.next(), Some(Err(Error::EndOfBuffer))));
+    assert!(iter.next().is_none());
+
+    // a value that reads nothing would be returned forever
+    struct Nothing;
+    impl<'de> serde::Deserialize<'de> for Nothing {
+        fn deserialize<D: serde::Deserializer<'de>>(_: D) -> Result<Self, D::Error> { Ok(Nothing) }
+    }
+    let mut iter = iter_from_slice::<Nothing>(&[0xc0]);
+    assert!(matches!(iter.next(), Some(Err(Error::InvalidType))));
+    assert!(iter.next().is_none());
+}
+#[test]
+fn decode_partial_and_exact() {
+    use embedded_msgpack::decode::{from_slice, from_slice_exact, from_slice_partial, Error};
+    let buf = [0x92, 0x01, 0xa1, b'a', 0xc3];
+    assert_eq!(from_slice_partial::<(u8, &str)>(&buf).unwrap(), ((1, "a"), 4));
+    assert_eq!(from_slice::<(u8, &str)>(&buf).unwrap(), (1, "a"));
+    assert!(matches!(from_slice_exact::<(u8, &str)>(&buf), Err(Error::TrailingBytes)));
+    assert_eq!(from_slice_exact::<(u8, &str)>(&buf[..4]).unwrap(), (1, "a"));
+    assert!(matches!(from_slice_exact::<(u8, &str)>(&buf[..3]), Err(Error::EndOfBuffer)));
+}
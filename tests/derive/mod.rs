@@ -0,0 +1,118 @@
+use embedded_msgpack::{
+    decode::{DeserializeFromSlice, Error},
+    encode::SerializeIntoSlice,
+};
+
+#[derive(SerializeIntoSlice, DeserializeFromSlice, Default, PartialEq, Debug)]
+struct Header {
+    src: Option<u8>,
+    #[msgpack(rename = "destination")]
+    dst: Option<u16>,
+    #[msgpack(skip)]
+    local: u8,
+}
+
+#[derive(SerializeIntoSlice, DeserializeFromSlice, Default, PartialEq, Debug)]
+#[msgpack(array)]
+struct Reading {
+    id: Option<u32>,
+    #[msgpack(skip)]
+    cached: bool,
+    value: Option<i16>,
+}
+
+#[derive(SerializeIntoSlice, DeserializeFromSlice, Default, PartialEq, Debug)]
+struct Pair(Option<u8>, Option<i8>);
+
+#[derive(SerializeIntoSlice, DeserializeFromSlice, Default, PartialEq, Debug)]
+struct Id(Option<u32>);
+
+#[derive(SerializeIntoSlice, DeserializeFromSlice, Default, PartialEq, Debug)]
+struct Message<T> {
+    hdr: Header,
+    body: T,
+}
+
+fn roundtrip<T: SerializeIntoSlice + DeserializeFromSlice + Default + PartialEq + core::fmt::Debug>(value: &T, expected: &[u8]) {
+    let mut buf = [0u8; 64];
+    let len = value.write_into_slice(&mut buf).unwrap();
+    assert_eq!(&buf[..len], expected);
+    let mut decoded = T::default();
+    assert_eq!(decoded.from_slice(&buf[..len]).unwrap(), len);
+    assert_eq!(&decoded, value);
+}
+
+#[test]
+fn derive_map() {
+    let hdr = Header {
+        src: Some(1),
+        dst: Some(2),
+        local: 0,
+    };
+    #[rustfmt::skip]
+    roundtrip(&hdr, &[
+        0x82,
+        0xa3, b's', b'r', b'c', 0x01,
+        0xab, b'd', b'e', b's', b't', b'i', b'n', b'a', b't', b'i', b'o', b'n', 0x02,
+    ]);
+
+    // unknown keys are skipped, missing and skipped fields are left alone
+    let mut hdr = Header {
+        src: Some(7),
+        dst: Some(9),
+        local: 3,
+    };
+    let buf = [
+        0x82, 0xa1, b'x', 0x92, 0x01, 0x02, 0xab, b'd', b'e', b's', b't', b'i', b'n', b'a', b't', b'i', b'o', b'n', 0xcd, 0x01, 0x00,
+    ];
+    assert_eq!(hdr.from_slice(&buf).unwrap(), buf.len());
+    assert_eq!(
+        hdr,
+        Header {
+            src: Some(7),
+            dst: Some(256),
+            local: 3
+        }
+    );
+}
+
+#[test]
+fn derive_array() {
+    let reading = Reading {
+        id: Some(300),
+        cached: false,
+        value: Some(-2),
+    };
+    roundtrip(&reading, &[0x92, 0xcd, 0x01, 0x2c, 0xfe]);
+    roundtrip(&Pair(Some(1), Some(-5)), &[0x92, 0x01, 0xfb]);
+    roundtrip(&Pair(None, Some(2)), &[0x92, 0xc0, 0x02]);
+    roundtrip(&Id(Some(5)), &[0x05]);
+
+    // surplus elements are skipped
+    let mut pair = Pair::default();
+    assert_eq!(pair.from_slice(&[0x93, 0x01, 0x02, 0xa1, b'x']).unwrap(), 5);
+    assert_eq!(pair, Pair(Some(1), Some(2)));
+}
+
+#[test]
+fn derive_nested() {
+    let msg = Message {
+        hdr: Header {
+            src: Some(1),
+            dst: Some(2),
+            local: 0,
+        },
+        body: Pair(Some(3), Some(-4)),
+    };
+    let mut buf = [0u8; 64];
+    let len = msg.write_into_slice(&mut buf).unwrap();
+    let mut decoded = Message::<Pair>::default();
+    assert_eq!(decoded.from_slice(&buf[..len]).unwrap(), len);
+    assert_eq!(decoded, msg);
+
+    assert!(matches!(decoded.from_slice(&buf[..len - 1]), Err(Error::EndOfBuffer)));
+    assert!(matches!(
+        decoded.from_slice(&[0x92]),
+        Err(Error::InvalidType) | Err(Error::EndOfBuffer)
+    ));
+}
@@ -273,3 +273,323 @@ fn encode_complex_struct() {
         ],
     );
 }
+#[test]
+fn encode_into_writer() {
+    use embedded_msgpack::encode::{serialize_u16, SliceWriter};
+    let mut buf = [0u8; 4];
+    let mut rest = &mut buf[..];
+    assert_eq!(serialize_u16(0x1234, &mut rest).unwrap(), 3);
+    assert_eq!(rest.len(), 1);
+    assert!(serialize_u16(0x1234, &mut rest).is_err());
+    assert_eq!(&buf[..3], &[0xcd, 0x12, 0x34]);
+
+    let mut buf = [0u8; 10];
+    let mut writer = SliceWriter::new(&mut buf);
+    "abc".write_into(&mut writer).unwrap();
+    1u8.write_into(&mut writer).unwrap();
+    assert_eq!(writer.position(), 5);
+    assert_eq!(writer.into_written(), &[0xa3, 0x61, 0x62, 0x63, 0x01]);
+}
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[test]
+fn encode_into_vec() {
+    let mut vec = Vec::new();
+    assert_eq!((&[1u32, 2, 3][..]).write_into(&mut vec).unwrap(), 4);
+    assert_eq!(true.write_into(&mut vec).unwrap(), 1);
+    assert_eq!(&vec, &[0x93, 1, 2, 3, 0xc3]);
+}
+#[cfg(feature = "std")]
+#[test]
+fn encode_into_io_writer() {
+    use embedded_msgpack::encode::IoWriter;
+    let mut out = std::io::Cursor::new(Vec::new());
+    assert_eq!(256u16.write_into(IoWriter(&mut out)).unwrap(), 3);
+    assert_eq!(out.get_ref(), &[0xcd, 0x01, 0x00]);
+}
+#[cfg(all(feature = "serde", any(feature = "alloc", feature = "std")))]
+#[test]
+fn encode_serde_to_writer() {
+    use serde::Serialize;
+    #[derive(Serialize)]
+    struct Test {
+        a: Option<i32>,
+        b: u32,
+    }
+    let mut vec = Vec::new();
+    let len = embedded_msgpack::encode::serde::to_writer(&Test { a: None, b: 1 }, &mut vec).unwrap();
+    assert_eq!(len, 7);
+    assert_eq!(&vec, &[0x82, 0xa1, 0x61, 0xc0, 0xa1, 0x62, 0x01]);
+}
+#[cfg(feature = "serde")]
+#[test]
+fn encode_unsized_seq() {
+    use serde::{Serialize, Serializer};
+    struct Evens<'a>(&'a [u8]);
+    impl<'a> Serialize for Evens<'a> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.0.iter().filter(|&&x| x % 2 == 0))
+        }
+    }
+    test_encode_serde(&Evens(&[1, 2, 3, 4, 5, 6]), &[0x93, 2, 4, 6]);
+    test_encode_serde(&Evens(&[]), &[0x90]);
+    test_encode_serde(&[Evens(&[2]), Evens(&[1, 4])], &[0x92, 0x91, 2, 0x91, 4]);
+    #[cfg(feature = "array16")]
+    {
+        let data = [2u8; 20];
+        let mut expected = vec![0xdc, 0x00, 20];
+        expected.extend_from_slice(&data);
+        test_encode_serde(&Evens(&data), &expected);
+    }
+
+    // writers that cannot back-patch the header reject sequences of unknown length
+    let mut buf = [0u8; 10];
+    assert!(embedded_msgpack::encode::serde::to_writer(&Evens(&[2]), &mut buf[..]).is_err());
+}
+#[cfg(feature = "serde")]
+#[test]
+fn encode_unsized_map() {
+    use serde::Serialize;
+    #[derive(Serialize)]
+    struct Inner {
+        b: u8,
+    }
+    #[derive(Serialize)]
+    struct Outer {
+        a: u8,
+        #[serde(flatten)]
+        inner: Inner,
+    }
+    test_encode_serde(
+        &Outer {
+            a: 1,
+            inner: Inner { b: 2 },
+        },
+        &[0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02],
+    );
+}
+#[cfg(feature = "serde")]
+#[test]
+fn encode_enum() {
+    use serde::Serialize;
+    #[derive(Serialize)]
+    enum Command {
+        Stop,
+        Set(u8),
+        Pair(u8, i8),
+        Move { x: i32 },
+    }
+    test_encode_serde(&Command::Stop, &[0xa4, 0x53, 0x74, 0x6f, 0x70]);
+    test_encode_serde(&Command::Set(5), &[0x81, 0xa3, 0x53, 0x65, 0x74, 0x05]);
+    test_encode_serde(&Command::Pair(1, -1), &[0x81, 0xa4, 0x50, 0x61, 0x69, 0x72, 0x92, 0x01, 0xff]);
+    test_encode_serde(
+        &Command::Move { x: -2 },
+        &[0x81, 0xa4, 0x4d, 0x6f, 0x76, 0x65, 0x81, 0xa1, 0x78, 0xfe],
+    );
+}
+#[cfg(feature = "serde")]
+#[test]
+fn encode_enum_variant_index() {
+    use embedded_msgpack::encode::serde::{to_array_with_config, Config};
+    use serde::Serialize;
+    #[derive(Serialize)]
+    enum Telemetry {
+        Idle,
+        Busy,
+        Temp(i8),
+    }
+    let config = Config::new().with_variant_index(true);
+    let mut buf = [0u8; 16];
+    let len = to_array_with_config(&[Telemetry::Idle, Telemetry::Busy], &mut buf, config).unwrap();
+    assert_eq!(&buf[..len], &[0x92, 0x00, 0x01]);
+    let len = to_array_with_config(&Telemetry::Temp(-5), &mut buf, config).unwrap();
+    assert_eq!(&buf[..len], &[0x81, 0x02, 0xfb]);
+}
+#[cfg(feature = "serde")]
+#[test]
+fn encode_struct_as_array() {
+    use embedded_msgpack::encode::serde::{to_array_with_config, Config};
+    use serde::Serialize;
+    #[derive(Serialize)]
+    struct Point {
+        x: u8,
+        y: i8,
+    }
+    #[derive(Serialize)]
+    enum Shape {
+        Dot { at: Point },
+    }
+    let config = Config::new().with_struct_as_array(true);
+    let mut buf = [0u8; 16];
+    let len = to_array_with_config(&Point { x: 1, y: -2 }, &mut buf, config).unwrap();
+    assert_eq!(&buf[..len], &[0x92, 0x01, 0xfe]);
+    let len = to_array_with_config(&Shape::Dot { at: Point { x: 3, y: 4 } }, &mut buf, config).unwrap();
+    assert_eq!(&buf[..len], &[0x81, 0xa3, b'D', b'o', b't', 0x91, 0x92, 0x03, 0x04]);
+}
+#[cfg(feature = "serde")]
+#[test]
+fn encode_custom_error() {
+    use embedded_msgpack::encode::{serde::to_array, Error};
+    use serde::{ser, Serialize, Serializer};
+    struct Percent(u8);
+    impl Serialize for Percent {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if self.0 > 100 {
+                return Err(ser::Error::custom("percentage out of range"));
+            }
+            serializer.serialize_u8(self.0)
+        }
+    }
+    let mut buf = [0u8; 16];
+    assert_eq!(to_array(&[Percent(5)], &mut buf).unwrap(), 2);
+    let err = to_array(&[Percent(5), Percent(101)], &mut buf).unwrap_err();
+    #[cfg(not(feature = "custom-error-messages"))]
+    assert!(matches!(err, Error::CustomError));
+    #[cfg(feature = "custom-error-messages")]
+    assert!(matches!(err, Error::CustomErrorWithMessage(ref msg) if msg == "percentage out of range"));
+}
+#[cfg(feature = "custom-error-messages")]
+#[test]
+fn encode_custom_error_truncated() {
+    use embedded_msgpack::encode::Error;
+    use serde::ser::Error as _;
+    // 100 bytes in a single fragment
+    let msg = "é".repeat(50);
+    let Error::CustomErrorWithMessage(err) = Error::custom(&msg) else {
+        panic!("no message")
+    };
+    assert_eq!(err.as_str(), &msg[..64]);
+    // cut off at a char boundary
+    let Error::CustomErrorWithMessage(err) = Error::custom(format_args!("x{}", msg)) else {
+        panic!("no message")
+    };
+    assert_eq!(err.as_str(), format!("x{}", &msg[..62]));
+}
+#[cfg(feature = "serde")]
+#[test]
+fn encode_unit_and_tuple_struct() {
+    use embedded_msgpack::encode::serde::to_array;
+    use serde::Serialize;
+    #[derive(Serialize)]
+    struct Ack;
+    #[derive(Serialize)]
+    struct Rgb(u8, u8, u8);
+    let mut buf = [0u8; 16];
+    let len = to_array(&(), &mut buf).unwrap();
+    assert_eq!(&buf[..len], &[0xc0]);
+    let len = to_array(&Ack, &mut buf).unwrap();
+    assert_eq!(&buf[..len], &[0xc0]);
+    let len = to_array(&Rgb(1, 2, 200), &mut buf).unwrap();
+    assert_eq!(&buf[..len], &[0x93, 0x01, 0x02, 0xcc, 0xc8]);
+}
+#[cfg(feature = "serde")]
+#[test]
+fn encode_collect_str() {
+    use embedded_msgpack::encode::serde::{to_array, to_writer};
+    use serde::{Serialize, Serializer};
+    struct Version(u8, u8, u8);
+    impl core::fmt::Display for Version {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result { write!(f, "{}.{}.{}", self.0, self.1, self.2) }
+    }
+    impl Serialize for Version {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> { serializer.collect_str(self) }
+    }
+    struct Repeat(&'static str, usize);
+    impl Serialize for Repeat {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(&format_args!("{:>1$}", self.0, self.1))
+        }
+    }
+    let expected = [0x92, 0xa6, b'1', b'.', b'2', b'.', b'3', b'0', 0x01];
+
+    // back-patched header
+    let mut buf = [0u8; 16];
+    let len = to_array(&(Version(1, 2, 30), 1), &mut buf).unwrap();
+    assert_eq!(&buf[..len], &expected);
+    // formatted twice
+    let mut buf = [0u8; 16];
+    let mut writer = &mut buf[..];
+    assert_eq!(to_writer(&(Version(1, 2, 30), 1), &mut writer).unwrap(), 9);
+    assert_eq!(&buf[..9], &expected);
+
+    let mut buf = [0u8; 64];
+    let len = to_array(&Repeat("x", 40), &mut buf).unwrap();
+    assert_eq!(len, 42);
+    assert_eq!(&buf[..3], &[0xd9, 40, b' ']);
+    assert_eq!(buf[41], b'x');
+    let mut writer = &mut buf[..];
+    assert_eq!(to_writer(&Repeat("x", 40), &mut writer).unwrap(), 42);
+
+    let mut buf = [0u8; 4];
+    assert!(to_array(&Version(1, 2, 30), &mut buf).is_err());
+    let mut writer = &mut buf[..];
+    assert!(to_writer(&Version(1, 2, 30), &mut writer).is_err());
+}
+#[test]
+fn encode_encoded_len() {
+    let mut buf = [0u8; 64];
+    assert_eq!(300u16.encoded_len().unwrap(), 3);
+    assert_eq!(Some(-1i32).encoded_len().unwrap(), 1);
+    assert_eq!("hello".encoded_len().unwrap(), "hello".write_into_slice(&mut buf).unwrap());
+    let bin = Binary::new(&[1, 2, 3]);
+    assert_eq!(bin.encoded_len().unwrap(), bin.write_into_slice(&mut buf).unwrap());
+}
+#[cfg(feature = "serde")]
+#[test]
+fn encode_serialized_size() {
+    use embedded_msgpack::encode::{
+        serde::{
+            required_buffer_len, required_buffer_len_with_config, serialized_size, serialized_size_with_config, to_array,
+            to_array_with_config, Config,
+        },
+        Error,
+    };
+    use serde::{Serialize, Serializer};
+    struct Unsized(u8);
+    impl Serialize for Unsized {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq((0..self.0).filter(|_| true))
+        }
+    }
+    #[derive(Serialize)]
+    struct Telemetry<'a> {
+        id: u32,
+        name: &'a str,
+        samples: Unsized,
+        label: core::fmt::Arguments<'a>,
+    }
+    let value = Telemetry {
+        id: 70000,
+        name: "probe",
+        samples: Unsized(20),
+        label: format_args!("{}-{}", "node", 7),
+    };
+    let mut buf = [0u8; 128];
+    let len = to_array(&value, &mut buf).unwrap();
+    assert_eq!(serialized_size(&value).unwrap(), len);
+    // the reserved headers of `samples` and `label` need room while they are written
+    let size = required_buffer_len(&value).unwrap();
+    assert!(size > len);
+    assert_eq!(to_array(&value, &mut buf[..size]).unwrap(), len);
+    assert!(matches!(to_array(&value, &mut buf[..size - 1]), Err(Error::EndOfBuffer)));
+    let config = Config::new().with_struct_as_array(true);
+    let len = to_array_with_config(&value, &mut buf, config).unwrap();
+    assert_eq!(serialized_size_with_config(&value, config).unwrap(), len);
+    let size = required_buffer_len_with_config(&value, config).unwrap();
+    assert_eq!(to_array_with_config(&value, &mut buf[..size], config).unwrap(), len);
+    assert!(matches!(
+        to_array_with_config(&value, &mut buf[..size - 1], config),
+        Err(Error::EndOfBuffer)
+    ));
+
+    // a filtered sequence has an unknown length, its 1 byte header is reserved with 5 bytes
+    let value = Unsized(3);
+    assert_eq!(serialized_size(&value).unwrap(), 4);
+    assert_eq!(required_buffer_len(&value).unwrap(), 8);
+    assert!(matches!(to_array(&value, &mut buf[..7]), Err(Error::EndOfBuffer)));
+    assert_eq!(to_array(&value, &mut buf[..8]).unwrap(), 4);
+    assert_eq!(&buf[..4], &[0x93, 0, 1, 2]);
+    // values of known length need no room beyond what is written
+    assert_eq!(serialized_size(&[1u8, 2, 3]).unwrap(), 4);
+    assert_eq!(required_buffer_len(&[1u8, 2, 3]).unwrap(), 4);
+    assert_eq!(to_array(&[1u8, 2, 3], &mut buf[..4]).unwrap(), 4);
+}
@@ -0,0 +1,64 @@
+use embedded_msgpack::decode::{message_length, Error, Framer, Messages};
+
+// [1, "ab"], {"k": nil}, true
+const STREAM: &[u8] = &[0x92, 0x01, 0xa2, b'a', b'b', 0x81, 0xa1, b'k', 0xc0, 0xc3];
+
+#[test]
+fn frame_message_length() {
+    assert_eq!(message_length(&[]).unwrap(), None);
+    for end in 1..5 {
+        assert_eq!(message_length(&STREAM[..end]).unwrap(), None);
+    }
+    assert_eq!(message_length(STREAM).unwrap(), Some(5));
+    assert_eq!(message_length(&STREAM[5..]).unwrap(), Some(4));
+    assert!(matches!(message_length(&[0xc1]), Err(Error::InvalidType)));
+    assert!(matches!(message_length(&[0x91, 0xc1]), Err(Error::InvalidType)));
+    // the largest lengths do not fit into a 32 bit usize together with the header
+    for data in [
+        &[0xdb, 0xff, 0xff, 0xff, 0xff][..],
+        &[0xc6, 0xff, 0xff, 0xff, 0xfe],
+        &[0xc9, 0xff, 0xff, 0xff, 0xfa, 0x01],
+    ] {
+        #[cfg(target_pointer_width = "32")]
+        assert!(matches!(message_length(data), Err(Error::OutOfBounds)));
+        #[cfg(not(target_pointer_width = "32"))]
+        assert_eq!(message_length(data).unwrap(), None);
+    }
+}
+#[test]
+fn frame_messages() {
+    let mut messages = Messages::new(&STREAM[..8]);
+    assert_eq!(messages.next().unwrap().unwrap(), &STREAM[..5]);
+    assert!(messages.next().is_none());
+    assert_eq!(messages.remainder(), &STREAM[5..8]);
+
+    let messages: Result<Vec<_>, _> = Messages::new(STREAM).collect();
+    assert_eq!(messages.unwrap(), [&STREAM[..5], &STREAM[5..9], &STREAM[9..]]);
+
+    let mut messages = Messages::new(&[0xc3, 0xc1, 0xc3]);
+    assert_eq!(messages.next().unwrap().unwrap(), &[0xc3]);
+    assert!(matches!(messages.next(), Some(Err(Error::InvalidType))));
+    assert!(messages.next().is_none());
+}
+#[test]
+fn frame_framer() {
+    let mut framer = Framer::<8>::new();
+    let mut received = Vec::new();
+    for byte in STREAM.chunks(3) {
+        assert_eq!(framer.push(byte), byte.len());
+        while let Some(message) = framer.next_message().unwrap() {
+            received.push(message.to_vec());
+        }
+    }
+    assert_eq!(received, [&STREAM[..5], &STREAM[5..9], &STREAM[9..]]);
+    assert!(framer.is_empty());
+}
+#[test]
+fn frame_framer_errors() {
+    let mut framer = Framer::<4>::new();
+    assert_eq!(framer.push(STREAM), 4);
+    assert!(matches!(framer.next_message(), Err(Error::OutOfBounds)));
+    framer.clear();
+    framer.push(&[0xc1]);
+    assert!(matches!(framer.next_message(), Err(Error::InvalidType)));
+}
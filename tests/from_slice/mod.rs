@@ -0,0 +1,93 @@
+use embedded_msgpack::{
+    decode::{DeserializeFromSlice, Error},
+    encode::SerializeIntoSlice,
+};
+
+fn roundtrip<T: SerializeIntoSlice + DeserializeFromSlice + Default + PartialEq + core::fmt::Debug>(value: T) {
+    let mut buf = [0u8; 64];
+    let len = value.write_into_slice(&mut buf).unwrap();
+    let mut decoded = T::default();
+    assert_eq!(decoded.from_slice(&buf[..len]).unwrap(), len);
+    assert_eq!(decoded, value);
+}
+
+#[test]
+fn from_slice_primitives() {
+    roundtrip(true);
+    roundtrip(200u8);
+    roundtrip(-300i16);
+    roundtrip(70000u32);
+    roundtrip(-70000i32);
+    roundtrip(Some(5u8));
+    roundtrip(Option::<u16>::None);
+    roundtrip(Some(Some(false)));
+    roundtrip([1u8, 2, 3]);
+    roundtrip([Some(1i8), None]);
+}
+#[cfg(feature = "f32")]
+#[test]
+fn from_slice_float() {
+    roundtrip(1.5f32);
+    roundtrip(-2.25f64);
+}
+#[cfg(feature = "i64")]
+#[test]
+fn from_slice_u64() {
+    roundtrip(u64::MAX);
+    roundtrip(i64::MIN);
+}
+/// Without the `i64` feature `read_sint` used to call itself through `read_i32` until the stack overflowed.
+#[test]
+fn from_slice_signed() {
+    use embedded_msgpack::decode::{read_i16, read_i32, read_i8};
+    assert_eq!(read_i8(&[0xff][..]).unwrap(), (-1, 1));
+    assert_eq!(read_i16(&[0xd1, 0xff, 0x00][..]).unwrap(), (-256, 3));
+    assert_eq!(read_i16(&[0xcd, 0x01, 0x00][..]).unwrap(), (256, 3));
+    assert_eq!(read_i32(&[0xd2, 0x80, 0x00, 0x00, 0x00][..]).unwrap(), (i32::MIN, 5));
+    assert!(matches!(read_i8(&[0xd1, 0x01, 0x00][..]), Err(Error::OutOfBounds)));
+    assert!(matches!(read_i32(&[0xce, 0x80, 0x00, 0x00, 0x00][..]), Err(Error::OutOfBounds)));
+    #[cfg(not(feature = "i64"))]
+    assert!(matches!(
+        read_i32(&[0xd3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01][..]),
+        Err(Error::UnsupportedType)
+    ));
+    roundtrip(-5i8);
+    roundtrip(i32::MIN);
+}
+#[test]
+fn from_slice_array_length() {
+    let mut arr = [0u8; 2];
+    assert!(matches!(arr.from_slice(&[0x93, 0x01, 0x02, 0x03]), Err(Error::OutOfBounds)));
+    assert!(matches!(arr.from_slice(&[0x92, 0x01]), Err(Error::EndOfBuffer)));
+}
+#[cfg(feature = "heapless")]
+#[test]
+fn from_slice_heapless() {
+    use core::convert::TryFrom;
+    roundtrip(heapless::String::<8>::try_from("hello").unwrap());
+    roundtrip(heapless::Vec::<u16, 4>::from_slice(&[1, 300, 70]).unwrap());
+
+    let mut s = heapless::String::<4>::new();
+    assert!(matches!(
+        s.from_slice(&[0xa5, b'h', b'e', b'l', b'l', b'o']),
+        Err(Error::OutOfBounds)
+    ));
+    let mut v = heapless::Vec::<u8, 2>::new();
+    assert!(matches!(v.from_slice(&[0x93, 0x01, 0x02, 0x03]), Err(Error::OutOfBounds)));
+}
+#[cfg(feature = "alloc")]
+#[test]
+fn from_slice_alloc() {
+    roundtrip(String::from("hello world"));
+    roundtrip(vec![Some(1u8), None, Some(3)]);
+}
+#[cfg(feature = "timestamp")]
+#[test]
+fn from_slice_timestamp() {
+    use embedded_msgpack::timestamp::Timestamp;
+    roundtrip(Timestamp::new(1, 0).unwrap());
+    roundtrip(Timestamp::new(1_600_000_000, 500).unwrap());
+    #[cfg(feature = "timestamp96")]
+    roundtrip(Timestamp::new(-5, 7).unwrap());
+    roundtrip(Some(Timestamp::new(2, 0).unwrap()));
+}
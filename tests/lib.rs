@@ -1,8 +1,20 @@
+#[cfg(feature = "embedded-io-async")]
+mod asynch;
+mod borrowed;
 mod encode;
+mod frame;
+mod from_slice;
+mod max_len;
 
 #[cfg(feature = "serde")]
 mod decode;
+#[cfg(feature = "derive")]
+mod derive;
 #[cfg(feature = "serde")]
 mod interop;
 #[cfg(feature = "serde")]
 mod roundtrip;
+mod skip;
+mod stream;
+mod value;
+mod view;
@@ -0,0 +1,148 @@
+use embedded_msgpack::encode::{max_len, MaxEncodedLen, SerializeIntoSlice};
+
+fn assert_max_len<T: MaxEncodedLen + SerializeIntoSlice>(worst: T) {
+    let mut buf = [0u8; 64];
+    assert_eq!(worst.write_into_slice(&mut buf).unwrap(), T::MAX_ENCODED_LEN);
+}
+
+#[test]
+fn max_len_primitives() {
+    assert_max_len(u8::MAX);
+    assert_max_len(u16::MAX);
+    assert_max_len(u32::MAX);
+    assert_max_len(i8::MIN);
+    assert_max_len(i16::MIN);
+    assert_max_len(i32::MIN);
+    assert_max_len(true);
+    assert_max_len(Some(u16::MAX));
+    assert_eq!(Option::<bool>::MAX_ENCODED_LEN, 1);
+    assert_eq!(<[u16; 20]>::MAX_ENCODED_LEN, 3 + 20 * 3);
+    assert_eq!(<(u8, bool)>::MAX_ENCODED_LEN, 4);
+    assert_eq!(<&u32>::MAX_ENCODED_LEN, 5);
+}
+#[cfg(feature = "u64")]
+#[test]
+fn max_len_u64() {
+    assert_max_len(u64::MAX);
+    assert_max_len(i64::MIN);
+}
+#[test]
+fn max_len_helpers() {
+    let mut buf = [0u8; 300];
+    for n in [0usize, 31, 32, 255] {
+        assert_eq!("x".repeat(n).as_str().write_into_slice(&mut buf).unwrap(), max_len::str_len(n));
+    }
+    assert_eq!(max_len::uint_len(127), 1);
+    assert_eq!(max_len::uint_len(128), 2);
+    #[cfg(feature = "fixext")]
+    assert_eq!(max_len::ext_len(4), 6);
+    #[cfg(not(feature = "fixext"))]
+    assert_eq!(max_len::ext_len(4), 7);
+    assert_eq!(max_len::ext_len(5), 8);
+}
+#[cfg(feature = "ext")]
+#[test]
+fn max_len_ext() {
+    use embedded_msgpack::Ext;
+    let mut buf = [0u8; 0x1_0010];
+    let data = [0u8; 0x1_0004];
+    for n in [0, 1, 4, 5, 16, 255, 256, 0xffff, 0x1_0000] {
+        // lengths the enabled features cannot encode fail instead
+        if let Ok(len) = (&Ext::new(1, &data[..n])).write_into_slice(&mut buf) {
+            assert_eq!(len, max_len::ext_len(n));
+            assert!(len <= Ext::MAX_ENCODED_LEN);
+        }
+    }
+    #[cfg(all(feature = "ext8", not(any(feature = "ext16", feature = "ext32"))))]
+    assert_eq!(
+        (&Ext::new(1, &data[..255])).write_into_slice(&mut buf).unwrap(),
+        Ext::MAX_ENCODED_LEN
+    );
+}
+#[cfg(feature = "timestamp96")]
+#[test]
+fn max_len_timestamp() {
+    use embedded_msgpack::timestamp::Timestamp;
+    assert_max_len(Timestamp::new(-1, 999_999_999).unwrap());
+}
+#[cfg(feature = "heapless")]
+#[test]
+fn max_len_heapless() {
+    assert_eq!(heapless::String::<40>::MAX_ENCODED_LEN, 42);
+    assert_eq!(heapless::Vec::<u16, 4>::MAX_ENCODED_LEN, 13);
+}
+
+#[cfg(all(feature = "derive", feature = "serde"))]
+mod derive {
+    use embedded_msgpack::encode::{
+        max_len,
+        serde::{serialized_size_with_config, Config},
+        MaxEncodedLen,
+    };
+    use serde::Serialize;
+
+    fn sizes<T: Serialize>(value: &T) -> [usize; 4] {
+        let mut sizes = [0; 4];
+        for (i, size) in sizes.iter_mut().enumerate() {
+            let config = Config::new().with_variant_index(i & 1 != 0).with_struct_as_array(i & 2 != 0);
+            *size = serialized_size_with_config(value, config).unwrap();
+        }
+        sizes
+    }
+
+    #[derive(Serialize, MaxEncodedLen)]
+    struct Header {
+        src: u8,
+        #[serde(rename = "destination")]
+        dst: u16,
+    }
+    #[derive(Serialize, MaxEncodedLen)]
+    struct Rgb(u8, u8, u8);
+    #[derive(Serialize, MaxEncodedLen)]
+    struct Ack;
+    #[derive(Serialize, MaxEncodedLen)]
+    enum Command<'a> {
+        Ping,
+        Color(Rgb),
+        Move(i16, i16),
+        Label {
+            #[msgpack(max_len = max_len::str_len(8))]
+            text: &'a str,
+        },
+    }
+    #[derive(Serialize, MaxEncodedLen)]
+    struct Message<'a, T> {
+        hdr: Header,
+        ack: Option<Ack>,
+        cmd: Command<'a>,
+        payload: [T; 2],
+    }
+
+    #[test]
+    fn max_len_derive() {
+        assert_eq!(Header::MAX_ENCODED_LEN, 1 + 4 + 2 + 12 + 3);
+        assert_eq!(Rgb::MAX_ENCODED_LEN, 7);
+        assert_eq!(Ack::MAX_ENCODED_LEN, 1);
+        let worst = Header { src: 255, dst: 65535 };
+        assert_eq!(sizes(&worst).iter().max(), Some(&Header::MAX_ENCODED_LEN));
+
+        // each variant fits and the largest one is exact
+        let commands = [
+            Command::Ping,
+            Command::Color(Rgb(255, 255, 255)),
+            Command::Move(i16::MIN, i16::MIN),
+            Command::Label { text: "12345678" },
+        ];
+        let largest = commands.iter().map(|c| *sizes(c).iter().max().unwrap()).max().unwrap();
+        assert_eq!(largest, Command::MAX_ENCODED_LEN);
+
+        let msg = Message {
+            hdr: worst,
+            ack: Some(Ack),
+            cmd: Command::Label { text: "12345678" },
+            payload: [u32::MAX; 2],
+        };
+        assert!(sizes(&msg).iter().all(|&s| s <= Message::<u32>::MAX_ENCODED_LEN));
+        let _buf = [0u8; Message::<u32>::MAX_ENCODED_LEN];
+    }
+}
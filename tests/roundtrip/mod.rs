@@ -192,3 +192,126 @@ fn roundtrip_complex_struct() {
         c: (2, [3, 4, 5]),
     });
 }
+#[test]
+fn roundtrip_enum() {
+    use serde::{Deserialize, Serialize};
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Command {
+        Stop,
+        Set(u8),
+        Pair(u8, i8),
+        Move { x: i32, y: Option<u16> },
+    }
+    test_roundtrip(Command::Stop);
+    test_roundtrip(Command::Set(200));
+    test_roundtrip(Command::Pair(1, -100));
+    test_roundtrip(Command::Move { x: -70000, y: Some(3) });
+    test_roundtrip([Command::Set(1), Command::Stop, Command::Pair(2, 3)]);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Wrapper {
+        cmd: Command,
+        seq: u8,
+    }
+    test_roundtrip(Wrapper {
+        cmd: Command::Move { x: 1, y: None },
+        seq: 7,
+    });
+}
+#[test]
+fn roundtrip_enum_skipped_field() {
+    use serde::{Deserialize, Serialize};
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Cmd {
+        Move {
+            x: i32,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            y: Option<u16>,
+        },
+    }
+    let mut buf = [0u8; 16];
+    let len = embedded_msgpack::encode::serde::to_array(&Cmd::Move { x: 1, y: None }, &mut buf).unwrap();
+    // {"Move": {"x": 1}}
+    assert_eq!(&buf[..len], &[0x81, 0xa4, b'M', b'o', b'v', b'e', 0x81, 0xa1, b'x', 0x01]);
+    test_roundtrip(Cmd::Move { x: 1, y: None });
+    test_roundtrip(Cmd::Move { x: 1, y: Some(2) });
+}
+#[test]
+fn roundtrip_enum_variant_index() {
+    use embedded_msgpack::encode::serde::{to_array_with_config, Config};
+    use serde::{Deserialize, Serialize};
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Command {
+        Stop,
+        Set(u8),
+        Move { x: i32 },
+    }
+    let data = [Command::Stop, Command::Set(3), Command::Move { x: -1 }];
+    let mut buf = [0u8; 100];
+    let len = to_array_with_config(&data, &mut buf, Config::new().with_variant_index(true)).unwrap();
+    print_slice(&buf[..len]);
+    let v: [Command; 3] = embedded_msgpack::decode::from_slice(&buf[..len]).unwrap();
+    assert_eq!(data, v);
+}
+#[test]
+fn roundtrip_struct_as_array() {
+    use embedded_msgpack::encode::serde::{to_array_with_config, Config};
+    use serde::{Deserialize, Serialize};
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Reading<'a> {
+        id: u16,
+        name: &'a str,
+        values: [i32; 2],
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Event<'a> {
+        Sample {
+            #[serde(borrow)]
+            reading: Reading<'a>,
+            ok: bool,
+        },
+    }
+    let data = Event::Sample {
+        reading: Reading {
+            id: 7,
+            name: "temp",
+            values: [-1, 100_000],
+        },
+        ok: true,
+    };
+    let mut buf = [0u8; 100];
+    let len = to_array_with_config(&data, &mut buf, Config::new().with_struct_as_array(true)).unwrap();
+    print_slice(&buf[..len]);
+    let v: Event = embedded_msgpack::decode::from_slice(&buf[..len]).unwrap();
+    assert_eq!(data, v);
+
+    // additional trailing elements written by a newer version are skipped
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Old {
+        id: u16,
+    }
+    let v: [Old; 2] = embedded_msgpack::decode::from_slice(&[0x92, 0x93, 0x01, 0xc3, 0xa1, b'x', 0x91, 0x02]).unwrap();
+    assert_eq!(v, [Old { id: 1 }, Old { id: 2 }]);
+}
+#[test]
+fn roundtrip_unit_and_tuple_struct() {
+    use serde::{Deserialize, Serialize};
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Ack;
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Rgb(u8, u8, u8);
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Status {
+        ack: Ack,
+        color: Rgb,
+        reserved: (),
+    }
+    test_roundtrip(());
+    test_roundtrip(Ack);
+    test_roundtrip(Rgb(1, 2, 200));
+    test_roundtrip(Status {
+        ack: Ack,
+        color: Rgb(0, 128, 255),
+        reserved: (),
+    });
+}
@@ -0,0 +1,184 @@
+use embedded_msgpack::decode::{read_array_len, read_map_len, skip_any, skip_any_with_max_depth, Error};
+
+/// The recursive implementation `skip_any` had before it was made iterative, apart from rejecting the reserved
+/// marker `0xc1` like `skip_any` does now.
+fn skip_any_recursive(buf: &[u8]) -> Result<usize, Error> {
+    if buf.is_empty() {
+        return Ok(0);
+    }
+    let be16 = |buf: &[u8]| {
+        buf.get(1..3)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+            .ok_or(Error::EndOfBuffer)
+    };
+    let be32 = |buf: &[u8]| {
+        buf.get(1..5)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or(Error::EndOfBuffer)
+    };
+    let n = match buf[0] {
+        0x00..=0x7f | 0xe0..=0xff | 0xc0 | 0xc2 | 0xc3 => 1,
+        0xc1 => return Err(Error::InvalidType),
+        0xcc | 0xd0 => 2,
+        0xcd | 0xd1 => 3,
+        0xce | 0xd2 | 0xca => 5,
+        0xcf | 0xd3 | 0xcb => 9,
+        0xa0..=0xbf => (buf[0] & 0x1f) as usize + 1,
+        0xd9 | 0xc4 => 2 + *buf.get(1).ok_or(Error::EndOfBuffer)? as usize,
+        0xda | 0xc5 => 3 + be16(buf)?,
+        0xdb | 0xc6 => 5 + be32(buf)?,
+        0x90..=0x9f | 0xdc | 0xdd => {
+            let (len, mut n) = read_array_len(buf)?;
+            for _ in 0..len {
+                n += skip_any_recursive(&buf[n..])?;
+            }
+            n
+        }
+        0x80..=0x8f | 0xde | 0xdf => {
+            let (len, mut n) = read_map_len(buf)?;
+            for _ in 0..len * 2 {
+                n += skip_any_recursive(&buf[n..])?;
+            }
+            n
+        }
+        0xd4 => 3,
+        0xd5 => 4,
+        0xd6 => 6,
+        0xd7 => 10,
+        0xd8 => 18,
+        0xc7 => 3 + *buf.get(1).ok_or(Error::EndOfBuffer)? as usize,
+        0xc8 => 4 + be16(buf)?,
+        0xc9 => 6 + be32(buf)?,
+    };
+    if buf.len() < n {
+        return Err(Error::EndOfBuffer);
+    }
+    Ok(n)
+}
+
+/// Small deterministic PRNG, so failures are reproducible.
+struct Rng(u32);
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+    fn below(&mut self, n: u32) -> usize { (self.next() % n) as usize }
+}
+
+/// Appends a random but well-formed value to `out`. Only uses formats that need no features.
+fn random_value(rng: &mut Rng, depth: usize, out: &mut Vec<u8>) {
+    // containers get rarer further down to keep the messages small
+    let kind = if depth == 0 || rng.below(4) >= depth {
+        rng.below(8)
+    } else {
+        rng.below(11)
+    };
+    match kind {
+        0 => out.push(rng.below(0x80) as u8),
+        1 => out.extend_from_slice(&[0xcd, 0x12, 0x34]),
+        2 => out.extend_from_slice(&[0xd2, 0xff, 0xff, 0xff, 0x00]),
+        3 => out.extend_from_slice(&[0xcb, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0]),
+        4 => out.extend_from_slice(&[0xc0, 0xc2, 0xc3][rng.below(3)..][..1]),
+        5 => {
+            let len = rng.below(40);
+            if len < 32 {
+                out.push(0xa0 | len as u8);
+            } else {
+                out.extend_from_slice(&[0xd9, len as u8]);
+            }
+            out.extend(core::iter::repeat_n(b'x', len));
+        }
+        6 => {
+            let len = rng.below(256);
+            out.extend_from_slice(&[0xc4, len as u8]);
+            out.extend(core::iter::repeat_n(0xaa, len));
+        }
+        7 => {
+            let ext: [&[u8]; 2] = [&[0xd4, 0x01, 0x02], &[0xc7, 0x03, 0x05, 1, 2, 3]];
+            out.extend_from_slice(ext[rng.below(2)]);
+        }
+        8 | 9 => {
+            let len = rng.below(16);
+            out.push(0x90 | len as u8);
+            for _ in 0..len {
+                random_value(rng, depth - 1, out);
+            }
+        }
+        _ => {
+            let len = rng.below(16);
+            out.push(0x80 | len as u8);
+            for _ in 0..len * 2 {
+                random_value(rng, depth - 1, out);
+            }
+        }
+    }
+}
+
+fn assert_same(buf: &[u8]) {
+    let iterative = skip_any(buf).map(|(_, n)| n);
+    // nothing generated here is nested that deep
+    let limited = skip_any_with_max_depth::<32>(buf).map(|(_, n)| n);
+    assert_eq!(format!("{:?}", iterative), format!("{:?}", limited), "{:x?}", buf);
+    match (skip_any_recursive(buf), iterative) {
+        (Ok(a), Ok(b)) => assert_eq!(a, b, "{:x?}", buf),
+        (Err(a), Err(b)) => assert_eq!(format!("{:?}", a), format!("{:?}", b), "{:x?}", buf),
+        // The recursive version returned `Ok(0)` for an element that was cut off entirely and thereby accepted
+        // containers truncated at an element boundary.
+        (Ok(_), Err(Error::EndOfBuffer)) => {}
+        (a, b) => panic!("{:?} != {:?} for {:x?}", a, b, buf),
+    }
+}
+
+#[test]
+fn skip_any_matches_recursive() {
+    let mut rng = Rng(0x2545_f491);
+    for _ in 0..500 {
+        let mut buf = Vec::new();
+        random_value(&mut rng, 3, &mut buf);
+        // trailing data is not part of the value
+        let len = buf.len();
+        buf.extend_from_slice(&[0xc3, 0x91]);
+        assert_eq!(skip_any(&buf[..]).unwrap().1, len);
+        assert_same(&buf);
+        for end in 0..len {
+            assert_same(&buf[..end]);
+        }
+    }
+}
+#[test]
+fn skip_any_matches_recursive_on_garbage() {
+    let mut rng = Rng(0x1234_5678);
+    for _ in 0..5000 {
+        let len = rng.below(24);
+        let buf: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+        assert_same(&buf);
+    }
+}
+#[test]
+fn skip_any_truncated() {
+    assert_eq!(skip_any(&[][..]).unwrap().1, 0);
+    assert!(matches!(skip_any(&[0x92, 0x91, 0x01][..]), Err(Error::EndOfBuffer)));
+    assert!(matches!(skip_any(&[0x81, 0xa1][..]), Err(Error::EndOfBuffer)));
+}
+#[test]
+fn skip_any_reserved_marker() {
+    assert!(matches!(skip_any(&[0xc1][..]), Err(Error::InvalidType)));
+    assert!(matches!(skip_any(&[0x92, 0x01, 0xc1][..]), Err(Error::InvalidType)));
+    assert!(matches!(skip_any(&[0x81, 0xc1, 0xc0][..]), Err(Error::InvalidType)));
+    // only a marker, not a data byte
+    assert_eq!(skip_any(&[0xa1, 0xc1][..]).unwrap().1, 2);
+    assert_eq!(skip_any(&[0xcc, 0xc1][..]).unwrap().1, 2);
+}
+#[test]
+fn skip_any_deeply_nested() {
+    // far deeper than the recursive version could handle on a small stack
+    let mut nested = vec![0x91u8; 100_000];
+    nested.push(0xc0);
+    assert_eq!(skip_any(&nested[..]).unwrap().1, nested.len());
+    let mut nested = [0x81u8, 0xc0].repeat(50_000);
+    nested.push(0xc0);
+    assert_eq!(skip_any(&nested[..]).unwrap().1, nested.len());
+}
@@ -0,0 +1,97 @@
+use embedded_msgpack::decode::{Error, Read, StreamDecoder, Token};
+
+// {"id": 300, "tags": ["a", nil], "neg": -2}
+const MESSAGE: &[u8] = &[
+    0x83, 0xa2, b'i', b'd', 0xcd, 0x01, 0x2c, 0xa4, b't', b'a', b'g', b's', 0x92, 0xa1, b'a', 0xc0, 0xa3, b'n', b'e', b'g', 0xfe,
+];
+const TOKENS: &[Token<'static>] = &[
+    Token::MapStart(3),
+    Token::Str("id"),
+    Token::UInt(300),
+    Token::Str("tags"),
+    Token::ArrayStart(2),
+    Token::Str("a"),
+    Token::Nil,
+    Token::Str("neg"),
+    Token::Int(-2),
+];
+
+/// Source handing out the message in fragments of `chunk` bytes, one fragment per call.
+struct Fragments {
+    data: &'static [u8],
+    chunk: usize,
+}
+
+impl Read for Fragments {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let n = self.chunk.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn stream_slice() {
+    let mut decoder = StreamDecoder::<_, 8>::new(MESSAGE);
+    for token in TOKENS {
+        assert_eq!(&decoder.next_token().unwrap(), token);
+    }
+    assert!(matches!(decoder.next_token(), Err(Error::NeedMoreData)));
+    assert!(decoder.get_ref().is_empty());
+}
+#[test]
+fn stream_fragments() {
+    for chunk in 1..4 {
+        let mut decoder = StreamDecoder::<_, 8>::new(Fragments { data: MESSAGE, chunk });
+        for token in TOKENS {
+            assert_eq!(&decoder.next_token().unwrap(), token);
+        }
+    }
+}
+#[test]
+fn stream_need_more_data() {
+    let mut decoder = StreamDecoder::<_, 8>::new(&MESSAGE[..5]);
+    assert_eq!(decoder.next_token().unwrap(), Token::MapStart(3));
+    assert_eq!(decoder.next_token().unwrap(), Token::Str("id"));
+    assert!(matches!(decoder.next_token(), Err(Error::NeedMoreData)));
+    assert!(matches!(decoder.next_token(), Err(Error::NeedMoreData)));
+    *decoder.get_mut() = &MESSAGE[5..];
+    assert_eq!(decoder.next_token().unwrap(), Token::UInt(300));
+    assert_eq!(decoder.next_token().unwrap(), Token::Str("tags"));
+}
+#[test]
+fn stream_errors() {
+    let mut decoder = StreamDecoder::<_, 4>::new(&[0xa5, b'h', b'e', b'l', b'l', b'o'][..]);
+    assert!(matches!(decoder.next_token(), Err(Error::OutOfBounds)));
+    let mut decoder = StreamDecoder::<_, 4>::new(&[0xc1][..]);
+    assert!(matches!(decoder.next_token(), Err(Error::InvalidType)));
+}
+#[cfg(feature = "heapless")]
+#[test]
+fn stream_deque() {
+    let mut ring = heapless::Deque::<u8, 4>::new();
+    let mut decoder = StreamDecoder::<_, 8>::new(&mut ring);
+    let mut tokens = TOKENS.iter();
+    for &byte in MESSAGE {
+        decoder.get_mut().push_back(byte).unwrap();
+        loop {
+            match decoder.next_token() {
+                Ok(token) => assert_eq!(&token, tokens.next().unwrap()),
+                Err(Error::NeedMoreData) => break,
+                Err(e) => panic!("{:?}", e),
+            }
+        }
+    }
+    assert!(tokens.next().is_none());
+}
+#[cfg(feature = "std")]
+#[test]
+fn stream_io() {
+    use embedded_msgpack::decode::IoReader;
+    let mut decoder = StreamDecoder::<_, 8>::new(IoReader(std::io::Cursor::new(MESSAGE)));
+    for token in TOKENS {
+        assert_eq!(&decoder.next_token().unwrap(), token);
+    }
+    assert!(matches!(decoder.next_token(), Err(Error::NeedMoreData)));
+}
@@ -0,0 +1,104 @@
+use embedded_msgpack::{decode::Error, encode::SerializeIntoSlice, Value};
+
+// {"id": 7, "tags": ["a", -2], "raw": bin[1, 2], "ok": nil}
+const MESSAGE: &[u8] = &[
+    0x84, 0xa2, b'i', b'd', 0x07, 0xa4, b't', b'a', b'g', b's', 0x92, 0xa1, b'a', 0xfe, 0xa3, b'r', b'a', b'w', 0xc4, 0x02, 0x01, 0x02,
+    0xa2, b'o', b'k', 0xc0,
+];
+
+const NIL: Value = Value::Nil;
+
+#[test]
+fn value_decode_in() {
+    let mut arena = [NIL; 10];
+    let (v, len) = Value::decode_in(MESSAGE, &mut arena).unwrap();
+    assert_eq!(len, MESSAGE.len());
+    assert_eq!(v.get("id").and_then(Value::as_u64), Some(7));
+    let tags = v.get("tags").and_then(Value::as_array).unwrap();
+    assert_eq!(tags, &[Value::Str("a"), Value::Int(-2)]);
+    assert_eq!(v.get("raw").and_then(Value::as_bytes), Some(&[1u8, 2][..]));
+    assert!(v.get("ok").unwrap().is_nil());
+    assert_eq!(v.get("missing"), None);
+    assert_eq!(v.entries().unwrap().count(), 4);
+}
+#[test]
+fn value_decode_in_arena_too_small() {
+    let mut arena = [NIL; 9];
+    assert!(matches!(Value::decode_in(MESSAGE, &mut arena), Err(Error::OutOfBounds)));
+    assert_eq!(Value::decode_in(&[0xa1, b'x'], &mut []).unwrap(), (Value::Str("x"), 2));
+    assert!(matches!(Value::decode_in(&[0x91, 0x01], &mut []), Err(Error::OutOfBounds)));
+}
+#[test]
+fn value_decode_normalizes_integers() {
+    assert_eq!(Value::decode_in(&[0xd0, 0x05], &mut []).unwrap().0, Value::UInt(5));
+    assert_eq!(Value::decode_in(&[0xd1, 0xff, 0x00], &mut []).unwrap().0, Value::Int(-256));
+    assert_eq!(Value::decode_in(&[0xcd, 0x01, 0x00], &mut []).unwrap().0, Value::UInt(256));
+}
+#[test]
+fn value_write_into() {
+    let mut arena = [NIL; 10];
+    let (v, _) = Value::decode_in(MESSAGE, &mut arena).unwrap();
+    let mut buf = [0u8; 64];
+    let len = v.write_into_slice(&mut buf).unwrap();
+    assert_eq!(&buf[..len], MESSAGE);
+
+    let elements = [Value::UInt(1), Value::Int(-1), Value::Bool(true)];
+    let len = Value::array(&elements).write_into_slice(&mut buf).unwrap();
+    assert_eq!(&buf[..len], &[0x93, 0x01, 0xff, 0xc3]);
+
+    let entries = [Value::Str("a"), Value::UInt(1), Value::Str("b")];
+    let len = Value::map(&entries[..2]).write_into_slice(&mut buf).unwrap();
+    assert_eq!(&buf[..len], &[0x81, 0xa1, b'a', 0x01]);
+    // a key without value
+    assert!(matches!(
+        Value::map(&entries).write_into_slice(&mut buf),
+        Err(embedded_msgpack::encode::Error::InvalidType)
+    ));
+    #[cfg(feature = "serde")]
+    assert!(embedded_msgpack::encode::serde::to_array(&Value::map(&entries), &mut buf).is_err());
+}
+#[cfg(feature = "fixext")]
+#[test]
+fn value_ext() {
+    let data = [0xd5, 0x05, 0x01, 0x02];
+    let (v, len) = Value::decode_in(&data, &mut []).unwrap();
+    assert_eq!((v.clone(), len), (Value::Ext(5, &[1, 2]), 4));
+    let mut buf = [0u8; 8];
+    let len = v.write_into_slice(&mut buf).unwrap();
+    assert_eq!(&buf[..len], &data);
+}
+#[cfg(feature = "serde")]
+#[test]
+fn value_serialize() {
+    let mut arena = [NIL; 10];
+    let (v, _) = Value::decode_in(MESSAGE, &mut arena).unwrap();
+    let mut buf = [0u8; 64];
+    let len = embedded_msgpack::encode::serde::to_array(&v, &mut buf).unwrap();
+    assert_eq!(&buf[..len], MESSAGE);
+}
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[test]
+fn value_decode_alloc() {
+    let mut arena = [NIL; 10];
+    let (expected, _) = Value::decode_in(MESSAGE, &mut arena).unwrap();
+    let (v, len) = Value::decode(MESSAGE).unwrap();
+    assert_eq!(len, MESSAGE.len());
+    assert_eq!(v, expected);
+    let v: Value = embedded_msgpack::decode::from_slice(MESSAGE).unwrap();
+    assert_eq!(v, expected);
+}
+#[test]
+fn value_depth_limit() {
+    let mut nested = [0x91u8; 1024];
+    nested[1023] = 0xc0;
+    let mut arena = [NIL; 1023];
+    assert!(matches!(Value::decode_in(&nested, &mut arena), Err(Error::DepthLimitExceeded)));
+    let mut arena = [NIL; 3];
+    let (v, _) = Value::decode_in_with_max_depth(&nested[1024 - 4..], &mut arena, 3).unwrap();
+    assert!(v.as_array().unwrap()[0].as_array().unwrap()[0].as_array().unwrap()[0].is_nil());
+    let mut arena = [NIL; 3];
+    assert!(matches!(
+        Value::decode_in_with_max_depth(&nested[1024 - 4..], &mut arena, 2),
+        Err(Error::DepthLimitExceeded)
+    ));
+}
@@ -0,0 +1,82 @@
+use embedded_msgpack::decode::{Error, MessageView};
+
+// {"hdr": {"src": 1, "dst": 513}, "body": [true, "x", [-1]], 7: "int key"}
+const MESSAGE: &[u8] = &[
+    0x83, 0xa3, b'h', b'd', b'r', 0x82, 0xa3, b's', b'r', b'c', 0x01, 0xa3, b'd', b's', b't', 0xcd, 0x02, 0x01, 0xa4, b'b', b'o', b'd',
+    b'y', 0x93, 0xc3, 0xa1, b'x', 0x91, 0xff, 0x07, 0xa7, b'i', b'n', b't', b' ', b'k', b'e', b'y',
+];
+
+#[test]
+fn view_get() {
+    let view = MessageView::new(MESSAGE).unwrap();
+    assert!(view.is_map());
+    assert_eq!(view.len().unwrap(), 3);
+    assert_eq!(view.get("hdr").unwrap().get("dst").unwrap().as_u16().unwrap(), 513);
+    assert_eq!(view.get("hdr").unwrap().get("src").unwrap().as_u8().unwrap(), 1);
+    assert!(matches!(view.get("missing"), Err(Error::KeyNotFound)));
+    assert!(matches!(
+        view.get("hdr").unwrap().get("dst").unwrap().get("x"),
+        Err(Error::InvalidType)
+    ));
+    assert!(matches!(
+        view.get("hdr").unwrap().get("dst").unwrap().as_u8(),
+        Err(Error::OutOfBounds)
+    ));
+}
+#[test]
+fn view_index() {
+    let body = MessageView::new(MESSAGE).unwrap().get("body").unwrap();
+    assert!(body.is_array());
+    assert!(body.index(0).unwrap().as_bool().unwrap());
+    assert_eq!(body.index(1).unwrap().as_str().unwrap(), "x");
+    assert_eq!(body.index(2).unwrap().index(0).unwrap().as_i8().unwrap(), -1);
+    assert!(matches!(body.index(3), Err(Error::OutOfBounds)));
+    assert_eq!(body.index(2).unwrap().as_slice(), &[0x91, 0xff]);
+    assert_eq!(body.elements().unwrap().count(), 3);
+}
+#[test]
+fn view_entries() {
+    let view = MessageView::new(MESSAGE).unwrap();
+    let mut entries = view.entries().unwrap();
+    assert_eq!(entries.next().unwrap().unwrap().0.as_str().unwrap(), "hdr");
+    assert_eq!(entries.next().unwrap().unwrap().0.as_str().unwrap(), "body");
+    let (k, v) = entries.next().unwrap().unwrap();
+    assert_eq!(k.as_u8().unwrap(), 7);
+    assert_eq!(v.as_str().unwrap(), "int key");
+    assert!(entries.next().is_none());
+}
+#[test]
+fn view_truncated() {
+    assert!(matches!(MessageView::new(&[]), Err(Error::EndOfBuffer)));
+    assert!(matches!(MessageView::new(&MESSAGE[..MESSAGE.len() - 1]), Err(Error::EndOfBuffer)));
+    // trailing bytes are not part of the view
+    let view = MessageView::new(&[0xc0, 0x01]).unwrap();
+    assert!(view.is_nil());
+    assert_eq!(view.as_slice(), &[0xc0]);
+}
+#[cfg(feature = "serde")]
+#[test]
+fn view_from_slice() {
+    #[derive(serde::Deserialize, PartialEq, Debug)]
+    struct Header {
+        src: u8,
+        dst: u16,
+    }
+    let hdr = MessageView::new(MESSAGE).unwrap().get("hdr").unwrap();
+    let hdr: Header = embedded_msgpack::decode::from_slice(hdr.as_slice()).unwrap();
+    assert_eq!(hdr, Header { src: 1, dst: 513 });
+}
+#[test]
+fn view_depth_limit() {
+    let mut nested = [0x91u8; 1024];
+    nested[1023] = 0xc0;
+    assert!(matches!(MessageView::new(&nested), Err(Error::DepthLimitExceeded)));
+    let view = MessageView::with_max_depth::<40>(&nested[1024 - 41..]).unwrap();
+    assert!(view.index(0).unwrap().index(0).unwrap().is_array());
+    assert!(matches!(
+        MessageView::with_max_depth::<39>(&nested[1024 - 41..]),
+        Err(Error::DepthLimitExceeded)
+    ));
+    let view = MessageView::with_max_depth::<1024>(&nested).unwrap();
+    assert_eq!(view.as_slice().len(), 1024);
+}